@@ -0,0 +1,152 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmarks of parsing and percent-encoding on a corpus of real-world URLs.
+//!
+//! Run with `cargo bench`. The percent-encoding benchmarks compare with
+//! `table_percent_encode_to`, the previous byte-by-byte implementation.
+//!
+//! `cargo bench --features scalar_scan` makes the parser and the percent-encoder
+//! look up every byte in the stop table one by one, instead of eight bytes at a time.
+//! Compare the `parse` and `percent_encode_*` results of both runs to measure the word scan.
+
+#![feature(core, test)]
+
+extern crate test;
+extern crate url;
+
+use test::{Bencher, black_box};
+use url::{Url, UrlParser};
+use url::percent_encoding::{
+    percent_encode, percent_encode_to, EncodeSet,
+    DEFAULT_ENCODE_SET, QUERY_ENCODE_SET, FORM_URLENCODED_ENCODE_SET,
+};
+
+
+fn corpus() -> Vec<&'static str> {
+    include_str!("urls.txt").lines().filter(|line| !line.is_empty()).collect()
+}
+
+
+fn corpus_bytes(corpus: &[&str]) -> u64 {
+    corpus.iter().map(|input| input.len() as u64).fold(0, |a, b| a + b)
+}
+
+
+/// The previous implementation of `percent_encode_to`:
+/// one table lookup and one `push_str` for every byte.
+struct Table(Vec<String>);
+
+impl Table {
+    fn new(encode_set: EncodeSet) -> Table {
+        Table((0..256).map(|byte| percent_encode(&[byte as u8], encode_set)).collect())
+    }
+
+    fn table_percent_encode_to(&self, input: &[u8], output: &mut String) {
+        for &byte in input.iter() {
+            output.push_str(self.0[byte as usize].as_slice())
+        }
+    }
+}
+
+
+fn bench_percent_encode(b: &mut Bencher, encode_set: EncodeSet) {
+    let corpus = corpus();
+    let mut output = String::new();
+    b.bytes = corpus_bytes(corpus.as_slice());
+    b.iter(|| {
+        for input in corpus.iter() {
+            output.clear();
+            percent_encode_to(input.as_bytes(), encode_set, &mut output);
+            black_box(&output);
+        }
+    })
+}
+
+
+fn bench_table_percent_encode(b: &mut Bencher, encode_set: EncodeSet) {
+    let corpus = corpus();
+    let table = Table::new(encode_set);
+    let mut output = String::new();
+    b.bytes = corpus_bytes(corpus.as_slice());
+    b.iter(|| {
+        for input in corpus.iter() {
+            output.clear();
+            table.table_percent_encode_to(input.as_bytes(), &mut output);
+            black_box(&output);
+        }
+    })
+}
+
+
+#[bench]
+fn percent_encode_default(b: &mut Bencher) {
+    bench_percent_encode(b, DEFAULT_ENCODE_SET)
+}
+
+#[bench]
+fn table_percent_encode_default(b: &mut Bencher) {
+    bench_table_percent_encode(b, DEFAULT_ENCODE_SET)
+}
+
+#[bench]
+fn percent_encode_query(b: &mut Bencher) {
+    bench_percent_encode(b, QUERY_ENCODE_SET)
+}
+
+#[bench]
+fn table_percent_encode_query(b: &mut Bencher) {
+    bench_table_percent_encode(b, QUERY_ENCODE_SET)
+}
+
+#[bench]
+fn percent_encode_form_urlencoded(b: &mut Bencher) {
+    bench_percent_encode(b, FORM_URLENCODED_ENCODE_SET)
+}
+
+#[bench]
+fn table_percent_encode_form_urlencoded(b: &mut Bencher) {
+    bench_table_percent_encode(b, FORM_URLENCODED_ENCODE_SET)
+}
+
+
+#[bench]
+fn parse(b: &mut Bencher) {
+    let corpus = corpus();
+    b.bytes = corpus_bytes(corpus.as_slice());
+    b.iter(|| {
+        for input in corpus.iter() {
+            black_box(Url::parse(input));
+        }
+    })
+}
+
+
+#[bench]
+fn parse_into(b: &mut Bencher) {
+    let corpus = corpus();
+    let parser = UrlParser::new();
+    let mut url = Url::parse("http://example.com/").unwrap();
+    b.bytes = corpus_bytes(corpus.as_slice());
+    b.iter(|| {
+        for input in corpus.iter() {
+            black_box(parser.parse_into(input, &mut url));
+        }
+    })
+}
+
+
+#[bench]
+fn serialize(b: &mut Bencher) {
+    let urls: Vec<Url> = corpus().iter().filter_map(|input| Url::parse(input).ok()).collect();
+    b.iter(|| {
+        for url in urls.iter() {
+            black_box(url.serialize());
+        }
+    })
+}
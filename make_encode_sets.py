@@ -21,17 +21,31 @@ print('''\
 
 // Generated by make_encode_sets.py
 ''')
+tables = []
 for name, encoded in [
     ('SIMPLE',   ''),
     ('QUERY',    r''' "#<>`'''),
     ('DEFAULT',  r''' "#<>`?'''),
+    ('PATH_SEGMENT', r''' "#<>`?/%\\'''),
+    ('MATRIX_PARAM', r''' "#<>`?/%\\;='''),
     ('USERINFO', r''' "#<>`?@'''),
     ('PASSWORD', r''' "#<>`?@\/'''),
     ('USERNAME', r''' "#<>`?@\/:'''),
     ('FORM_URLENCODED', r''' !"#$%&\'()+,/:;<=>?@[\]^`{|}'''),
 ]:
-    print(
-        "pub static %s: [&'static str, ..256] = [\n%s\n];\n\n"
+    # The scanner in src/scan.rs skips ASCII letters and digits without a table lookup.
+    assert not any(c.isalnum() for c in encoded)
+    stops = [0 if 0x20 <= b <= 0x7E and chr(b) not in encoded else 1 for b in range(256)]
+    tables.append(
+        "pub static %s_PRINTABLE: &'static [u8] = b\"%s\";\n\n"
+        % (name, ''.join(
+            ("\\" if c in '\\"' else "") + c
+            for c in map(chr, range(0x20, 0x7F)) if c in encoded)) +
+        "pub static %s_STOPS: [u64; 4] = [\n    %s,\n];\n\n"
+        % (name, ', '.join(
+            '0x%016X' % sum(stops[s + j] << j for j in range(64))
+            for s in range(0, 256, 64))) +
+        "pub static %s: [&'static str; 256] = [\n%s\n];"
         % (name, '\n'.join(
             '   ' + ' '.join(
                 '"%s%s",' % ("\\" if chr(b) in '\\"' else "", chr(b))
@@ -39,3 +53,4 @@ for name, encoded in [
                 else '"%%%02X",' % b
                 for b in range(s, s + 8)
             ) for s in range(0, 256, 8))))
+print('\n\n\n'.join(tables))
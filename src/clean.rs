@@ -0,0 +1,398 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Removal of tracking parameters from query strings, and unwrapping of redirect links,
+//! for example before sharing a link.
+//!
+//! A `Cleaner` is configured with rules, one per line:
+//!
+//! ```text
+//! # Comments start with `#`.
+//! strip fbclid                 # Remove `fbclid` parameters on every host.
+//! strip utm_*                  # Remove parameters whose name starts with `utm_`.
+//! strip tag amazon.com         # Only on amazon.com and its subdomains.
+//! unwrap l.facebook.com/l.php u   # Follow the URL in the `u` parameter.
+//! unwrap www.google.com/url q url # … in the first of `q` or `url`.
+//! ```
+//!
+//! Parameter names are compared after `application/x-www-form-urlencoded` decoding.
+//! The parameters that are kept are not decoded or re-encoded, and keep their order.
+//!
+//! ```
+//! use url::Url;
+//! use url::clean::Cleaner;
+//!
+//! let cleaner = Cleaner::default();
+//! let url = Url::parse("https://example.com/?id=42&utm_source=chat&fbclid=x#top").unwrap();
+//! assert_eq!(cleaner.clean(&url).serialize(), "https://example.com/?id=42#top".to_string());
+//! ```
+
+use std::ascii::AsciiExt;
+use std::default::Default;
+use std::error::Error;
+use std::fmt::{self, Formatter};
+use std::io::{self, Read};
+use super::{Url, Host};
+use percent_encoding::lossy_utf8_percent_decode;
+
+
+/// The rules of `Cleaner::default()`.
+pub static DEFAULT_RULES: &'static str = "\
+strip utm_*
+strip fbclid
+strip gclid
+strip dclid
+strip msclkid
+strip mc_eid
+strip igshid
+strip yclid
+strip _hsenc
+strip _hsmi
+unwrap l.facebook.com/l.php u
+unwrap lm.facebook.com/l.php u
+unwrap www.google.com/url q url
+";
+
+
+/// The maximum number of nested redirect links that `Cleaner::clean` unwraps.
+const MAX_UNWRAP: usize = 5;
+
+
+/// A set of rules to clean URLs.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Cleaner {
+    strip: Vec<StripRule>,
+    unwrap: Vec<UnwrapRule>,
+}
+
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct StripRule {
+    name: String,
+    is_prefix: bool,
+    host: Option<String>,
+}
+
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct UnwrapRule {
+    host: String,
+    path: Option<String>,
+    params: Vec<String>,
+}
+
+
+/// An error in the syntax of rules, with its 1-based line number.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum RuleError {
+    /// The line does not start with `strip` or `unwrap`.
+    UnknownDirective(usize),
+
+    /// The line does not have enough arguments for its directive.
+    MissingArgument(usize),
+
+    /// The line has too many arguments for its directive.
+    TooManyArguments(usize),
+}
+
+
+impl Error for RuleError {
+    fn description(&self) -> &str {
+        match *self {
+            RuleError::UnknownDirective(_) => "unknown directive",
+            RuleError::MissingArgument(_) => "missing argument",
+            RuleError::TooManyArguments(_) => "too many arguments",
+        }
+    }
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let line = match *self {
+            RuleError::UnknownDirective(line) |
+            RuleError::MissingArgument(line) |
+            RuleError::TooManyArguments(line) => line,
+        };
+        write!(fmt, "{} on line {}", self.description(), line)
+    }
+}
+
+
+/// Errors that can occur when reading rules from an `io::Read` source.
+#[derive(Debug)]
+pub enum LoadError {
+    /// Reading failed, or the input is not UTF-8.
+    Io(io::Error),
+    /// The rules are invalid.
+    Rule(RuleError),
+}
+
+
+impl Error for LoadError {
+    fn description(&self) -> &str {
+        match *self {
+            LoadError::Io(ref error) => error.description(),
+            LoadError::Rule(ref error) => error.description(),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref error) => error.fmt(fmt),
+            LoadError::Rule(ref error) => error.fmt(fmt),
+        }
+    }
+}
+
+
+impl Default for Cleaner {
+    /// Return a `Cleaner` with the built-in `DEFAULT_RULES`.
+    fn default() -> Cleaner {
+        Cleaner::from_rules(DEFAULT_RULES).unwrap()
+    }
+}
+
+
+impl Cleaner {
+    /// Return a `Cleaner` without any rule.
+    #[inline]
+    pub fn new() -> Cleaner {
+        Cleaner { strip: Vec::new(), unwrap: Vec::new() }
+    }
+
+    /// Parse rules in the format described in the module documentation.
+    #[inline]
+    pub fn from_rules(rules: &str) -> Result<Cleaner, RuleError> {
+        let mut cleaner = Cleaner::new();
+        try!(cleaner.add_rules(rules));
+        Ok(cleaner)
+    }
+
+    /// Read and parse rules, such as from a file.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Cleaner, LoadError> {
+        let mut rules = String::new();
+        match reader.read_to_string(&mut rules) {
+            Ok(_) => Cleaner::from_rules(rules.as_slice()).map_err(LoadError::Rule),
+            Err(error) => Err(LoadError::Io(error)),
+        }
+    }
+
+    /// Parse rules and add them to this `Cleaner`, for example to extend the default rules.
+    ///
+    /// If there is an error, no rule is added.
+    pub fn add_rules(&mut self, rules: &str) -> Result<&mut Cleaner, RuleError> {
+        let mut strip = Vec::new();
+        let mut unwrap = Vec::new();
+        for (i, line) in rules.lines().enumerate() {
+            let line_number = i + 1;
+            let line = &line[..line.find('#').unwrap_or(line.len())];
+            let words: Vec<&str> = line.words().collect();
+            if words.is_empty() {
+                continue
+            }
+            match words[0] {
+                "strip" => {
+                    if words.len() < 2 {
+                        return Err(RuleError::MissingArgument(line_number))
+                    }
+                    if words.len() > 3 {
+                        return Err(RuleError::TooManyArguments(line_number))
+                    }
+                    strip.push(StripRule::new(words[1], words.get(2).map(|&host| host)));
+                },
+                "unwrap" => {
+                    if words.len() < 3 {
+                        return Err(RuleError::MissingArgument(line_number))
+                    }
+                    unwrap.push(UnwrapRule::new(words[1], &words[2..]));
+                },
+                _ => return Err(RuleError::UnknownDirective(line_number)),
+            }
+        }
+        self.strip.extend(strip.into_iter());
+        self.unwrap.extend(unwrap.into_iter());
+        Ok(self)
+    }
+
+    /// Remove query parameters named `name`, or starting with `name` without its trailing `*`.
+    /// If `host` is given, only on that host and its subdomains.
+    /// Return the `Cleaner`.
+    #[inline]
+    pub fn strip(&mut self, name: &str, host: Option<&str>) -> &mut Cleaner {
+        self.strip.push(StripRule::new(name, host));
+        self
+    }
+
+    /// Unwrap links to `host_and_path` (such as `l.facebook.com/l.php`, or just a host)
+    /// into the URL in the first of the `params` query parameters that is present.
+    /// Return the `Cleaner`.
+    #[inline]
+    pub fn unwrap_redirect(&mut self, host_and_path: &str, params: &[&str]) -> &mut Cleaner {
+        self.unwrap.push(UnwrapRule::new(host_and_path, params));
+        self
+    }
+
+    /// Return a cleaned copy of `url`.
+    ///
+    /// Redirect links are unwrapped first, if the URL in their parameter is valid
+    /// and in a relative scheme such as `http` or `https`.
+    /// Parameters are then removed from the query string,
+    /// and the query string is removed if no parameter is left.
+    pub fn clean(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        for _ in 0..MAX_UNWRAP {
+            match self.unwrap_target(&url) {
+                Some(target) => url = target,
+                None => break,
+            }
+        }
+        let host = match url.host() {
+            Some(&Host::Domain(ref domain)) => Some(domain.clone()),
+            _ => None,
+        };
+        if let Some(query) = url.query.take() {
+            let kept: Vec<&str> = query.as_slice().split('&').filter(|&pair| {
+                let name = decode(&pair[..pair.find('=').unwrap_or(pair.len())]);
+                !self.strip.iter().any(|rule| rule.matches(name.as_slice(), host.as_ref()))
+            }).collect();
+            url.query = if kept.is_empty() { None } else { Some(kept.connect("&")) };
+        }
+        url
+    }
+
+    fn unwrap_target(&self, url: &Url) -> Option<Url> {
+        let domain = match url.domain() {
+            Some(domain) => domain,
+            None => return None,
+        };
+        let path = url.serialize_path();
+        let query = match url.query {
+            Some(ref query) => query,
+            None => return None,
+        };
+        for rule in self.unwrap.iter() {
+            if rule.host.as_slice() != domain {
+                continue
+            }
+            if let Some(ref rule_path) = rule.path {
+                if path.as_ref().map(|path| path.as_slice()) != Some(rule_path.as_slice()) {
+                    continue
+                }
+            }
+            for param in rule.params.iter() {
+                let value = query.as_slice().split('&').filter_map(|pair| {
+                    let (name, value) = match pair.find('=') {
+                        Some(position) => (&pair[..position], &pair[position + 1..]),
+                        None => (pair, ""),
+                    };
+                    if decode(name) == *param { Some(decode(value)) } else { None }
+                }).next();
+                if let Some(value) = value {
+                    match Url::parse(value.as_slice()) {
+                        Ok(ref target) if target.relative_scheme_data().is_some() => {
+                            return Some(target.clone())
+                        },
+                        _ => {},
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+
+impl StripRule {
+    fn new(name: &str, host: Option<&str>) -> StripRule {
+        let is_prefix = name.ends_with("*");
+        StripRule {
+            name: if is_prefix { name[..name.len() - 1].to_string() } else { name.to_string() },
+            is_prefix: is_prefix,
+            host: host.map(|host| host.to_ascii_lowercase()),
+        }
+    }
+
+    fn matches(&self, name: &str, host: Option<&String>) -> bool {
+        let name_matches = if self.is_prefix {
+            name.starts_with(self.name.as_slice())
+        } else {
+            name == self.name.as_slice()
+        };
+        name_matches && match self.host {
+            None => true,
+            Some(ref rule_host) => host.map_or(false, |host| {
+                let host = host.as_slice();
+                let rule_host = rule_host.as_slice();
+                host == rule_host || (host.ends_with(rule_host) &&
+                                      host.as_bytes()[host.len() - rule_host.len() - 1] == b'.')
+            }),
+        }
+    }
+}
+
+
+impl UnwrapRule {
+    fn new(host_and_path: &str, params: &[&str]) -> UnwrapRule {
+        let (host, path) = match host_and_path.find('/') {
+            Some(position) => (&host_and_path[..position],
+                               Some(host_and_path[position..].to_string())),
+            None => (host_and_path, None),
+        };
+        UnwrapRule {
+            host: host.to_ascii_lowercase(),
+            path: path,
+            params: params.iter().map(|param| param.to_string()).collect(),
+        }
+    }
+}
+
+
+fn decode(input: &str) -> String {
+    let replaced: Vec<u8> = input.bytes().map(|b| if b == b'+' { b' ' } else { b }).collect();
+    lossy_utf8_percent_decode(replaced.as_slice())
+}
+
+
+#[test]
+fn test_clean() {
+    let cleaner = Cleaner::default();
+    fn check(cleaner: &Cleaner, input: &str, expected: &str) {
+        let url = Url::parse(input).unwrap();
+        assert_eq!(cleaner.clean(&url).serialize(), expected.to_string());
+    }
+
+    check(&cleaner, "http://example.com/?a=%41&utm_source=x&b+c=1+2&utm_medium&fbclid=y&&d",
+          "http://example.com/?a=%41&b+c=1+2&&d");
+    check(&cleaner, "http://example.com/?utm%5Fsource=x&gclid=y#utm_source=z",
+          "http://example.com/#utm_source=z");
+    check(&cleaner, "http://example.com/?utm=1", "http://example.com/?utm=1");
+    check(&cleaner, "http://example.com/?", "http://example.com/?");
+    check(&cleaner,
+          "https://l.facebook.com/l.php?u=http%3A%2F%2Fexample.com%2F%3Fid%3D1%26fbclid%3Dz&h=x",
+          "http://example.com/?id=1");
+    check(&cleaner, "https://www.google.com/url?sa=t&url=https://example.com/a",
+          "https://example.com/a");
+    check(&cleaner, "https://l.facebook.com/l.php?u=javascript:alert(1)",
+          "https://l.facebook.com/l.php?u=javascript:alert(1)");
+    check(&cleaner, "https://l.facebook.com/other?u=http://example.com/",
+          "https://l.facebook.com/other?u=http://example.com/");
+
+    let mut cleaner = Cleaner::from_rules("strip tag amazon.com  # per-host\n\nunwrap t.co x").unwrap();
+    cleaner.strip("ref*", None);
+    check(&cleaner, "https://www.amazon.com/dp?tag=a&ref_=b&x=1", "https://www.amazon.com/dp?x=1");
+    check(&cleaner, "https://notamazon.com/dp?tag=a&x=1", "https://notamazon.com/dp?tag=a&x=1");
+    check(&cleaner, "https://t.co/abc?x=https://amazon.com/?tag=1", "https://amazon.com/");
+
+    assert_eq!(Cleaner::from_rules("strip a\nremove b"), Err(RuleError::UnknownDirective(2)));
+    assert_eq!(Cleaner::from_rules("strip"), Err(RuleError::MissingArgument(1)));
+    assert_eq!(Cleaner::from_rules("unwrap example.com"), Err(RuleError::MissingArgument(1)));
+    assert_eq!(Cleaner::from_rules("strip a b c"), Err(RuleError::TooManyArguments(1)));
+    assert_eq!(Cleaner::from_reader(DEFAULT_RULES.as_bytes()).unwrap(), Cleaner::default());
+}
@@ -8,6 +8,12 @@
 
 // Generated by make_encode_sets.py
 
+pub static SIMPLE_PRINTABLE: &'static [u8] = b"";
+
+pub static SIMPLE_STOPS: [u64; 4] = [
+    0x00000000FFFFFFFF, 0x8000000000000000, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
 pub static SIMPLE: [&'static str; 256] = [
    "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
    "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
@@ -44,6 +50,12 @@ pub static SIMPLE: [&'static str; 256] = [
 ];
 
 
+pub static QUERY_PRINTABLE: &'static [u8] = b" \"#<>`";
+
+pub static QUERY_STOPS: [u64; 4] = [
+    0x5000000DFFFFFFFF, 0x8000000100000000, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
 pub static QUERY: [&'static str; 256] = [
    "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
    "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
@@ -80,6 +92,12 @@ pub static QUERY: [&'static str; 256] = [
 ];
 
 
+pub static DEFAULT_PRINTABLE: &'static [u8] = b" \"#<>?`";
+
+pub static DEFAULT_STOPS: [u64; 4] = [
+    0xD000000DFFFFFFFF, 0x8000000100000000, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
 pub static DEFAULT: [&'static str; 256] = [
    "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
    "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
@@ -116,6 +134,96 @@ pub static DEFAULT: [&'static str; 256] = [
 ];
 
 
+pub static PATH_SEGMENT_PRINTABLE: &'static [u8] = b" \"#%/<>?\\`";
+
+pub static PATH_SEGMENT_STOPS: [u64; 4] = [
+    0xD000802DFFFFFFFF, 0x8000000110000000, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
+pub static PATH_SEGMENT: [&'static str; 256] = [
+   "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
+   "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
+   "%10", "%11", "%12", "%13", "%14", "%15", "%16", "%17",
+   "%18", "%19", "%1A", "%1B", "%1C", "%1D", "%1E", "%1F",
+   "%20", "!", "%22", "%23", "$", "%25", "&", "'",
+   "(", ")", "*", "+", ",", "-", ".", "%2F",
+   "0", "1", "2", "3", "4", "5", "6", "7",
+   "8", "9", ":", ";", "%3C", "=", "%3E", "%3F",
+   "@", "A", "B", "C", "D", "E", "F", "G",
+   "H", "I", "J", "K", "L", "M", "N", "O",
+   "P", "Q", "R", "S", "T", "U", "V", "W",
+   "X", "Y", "Z", "[", "%5C", "]", "^", "_",
+   "%60", "a", "b", "c", "d", "e", "f", "g",
+   "h", "i", "j", "k", "l", "m", "n", "o",
+   "p", "q", "r", "s", "t", "u", "v", "w",
+   "x", "y", "z", "{", "|", "}", "~", "%7F",
+   "%80", "%81", "%82", "%83", "%84", "%85", "%86", "%87",
+   "%88", "%89", "%8A", "%8B", "%8C", "%8D", "%8E", "%8F",
+   "%90", "%91", "%92", "%93", "%94", "%95", "%96", "%97",
+   "%98", "%99", "%9A", "%9B", "%9C", "%9D", "%9E", "%9F",
+   "%A0", "%A1", "%A2", "%A3", "%A4", "%A5", "%A6", "%A7",
+   "%A8", "%A9", "%AA", "%AB", "%AC", "%AD", "%AE", "%AF",
+   "%B0", "%B1", "%B2", "%B3", "%B4", "%B5", "%B6", "%B7",
+   "%B8", "%B9", "%BA", "%BB", "%BC", "%BD", "%BE", "%BF",
+   "%C0", "%C1", "%C2", "%C3", "%C4", "%C5", "%C6", "%C7",
+   "%C8", "%C9", "%CA", "%CB", "%CC", "%CD", "%CE", "%CF",
+   "%D0", "%D1", "%D2", "%D3", "%D4", "%D5", "%D6", "%D7",
+   "%D8", "%D9", "%DA", "%DB", "%DC", "%DD", "%DE", "%DF",
+   "%E0", "%E1", "%E2", "%E3", "%E4", "%E5", "%E6", "%E7",
+   "%E8", "%E9", "%EA", "%EB", "%EC", "%ED", "%EE", "%EF",
+   "%F0", "%F1", "%F2", "%F3", "%F4", "%F5", "%F6", "%F7",
+   "%F8", "%F9", "%FA", "%FB", "%FC", "%FD", "%FE", "%FF",
+];
+
+
+pub static MATRIX_PARAM_PRINTABLE: &'static [u8] = b" \"#%/;<=>?\\`";
+
+pub static MATRIX_PARAM_STOPS: [u64; 4] = [
+    0xF800802DFFFFFFFF, 0x8000000110000000, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
+pub static MATRIX_PARAM: [&'static str; 256] = [
+   "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
+   "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
+   "%10", "%11", "%12", "%13", "%14", "%15", "%16", "%17",
+   "%18", "%19", "%1A", "%1B", "%1C", "%1D", "%1E", "%1F",
+   "%20", "!", "%22", "%23", "$", "%25", "&", "'",
+   "(", ")", "*", "+", ",", "-", ".", "%2F",
+   "0", "1", "2", "3", "4", "5", "6", "7",
+   "8", "9", ":", "%3B", "%3C", "%3D", "%3E", "%3F",
+   "@", "A", "B", "C", "D", "E", "F", "G",
+   "H", "I", "J", "K", "L", "M", "N", "O",
+   "P", "Q", "R", "S", "T", "U", "V", "W",
+   "X", "Y", "Z", "[", "%5C", "]", "^", "_",
+   "%60", "a", "b", "c", "d", "e", "f", "g",
+   "h", "i", "j", "k", "l", "m", "n", "o",
+   "p", "q", "r", "s", "t", "u", "v", "w",
+   "x", "y", "z", "{", "|", "}", "~", "%7F",
+   "%80", "%81", "%82", "%83", "%84", "%85", "%86", "%87",
+   "%88", "%89", "%8A", "%8B", "%8C", "%8D", "%8E", "%8F",
+   "%90", "%91", "%92", "%93", "%94", "%95", "%96", "%97",
+   "%98", "%99", "%9A", "%9B", "%9C", "%9D", "%9E", "%9F",
+   "%A0", "%A1", "%A2", "%A3", "%A4", "%A5", "%A6", "%A7",
+   "%A8", "%A9", "%AA", "%AB", "%AC", "%AD", "%AE", "%AF",
+   "%B0", "%B1", "%B2", "%B3", "%B4", "%B5", "%B6", "%B7",
+   "%B8", "%B9", "%BA", "%BB", "%BC", "%BD", "%BE", "%BF",
+   "%C0", "%C1", "%C2", "%C3", "%C4", "%C5", "%C6", "%C7",
+   "%C8", "%C9", "%CA", "%CB", "%CC", "%CD", "%CE", "%CF",
+   "%D0", "%D1", "%D2", "%D3", "%D4", "%D5", "%D6", "%D7",
+   "%D8", "%D9", "%DA", "%DB", "%DC", "%DD", "%DE", "%DF",
+   "%E0", "%E1", "%E2", "%E3", "%E4", "%E5", "%E6", "%E7",
+   "%E8", "%E9", "%EA", "%EB", "%EC", "%ED", "%EE", "%EF",
+   "%F0", "%F1", "%F2", "%F3", "%F4", "%F5", "%F6", "%F7",
+   "%F8", "%F9", "%FA", "%FB", "%FC", "%FD", "%FE", "%FF",
+];
+
+
+pub static USERINFO_PRINTABLE: &'static [u8] = b" \"#<>?@`";
+
+pub static USERINFO_STOPS: [u64; 4] = [
+    0xD000000DFFFFFFFF, 0x8000000100000001, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
 pub static USERINFO: [&'static str; 256] = [
    "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
    "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
@@ -152,6 +260,12 @@ pub static USERINFO: [&'static str; 256] = [
 ];
 
 
+pub static PASSWORD_PRINTABLE: &'static [u8] = b" \"#/<>?@\\`";
+
+pub static PASSWORD_STOPS: [u64; 4] = [
+    0xD000800DFFFFFFFF, 0x8000000110000001, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
 pub static PASSWORD: [&'static str; 256] = [
    "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
    "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
@@ -188,6 +302,12 @@ pub static PASSWORD: [&'static str; 256] = [
 ];
 
 
+pub static USERNAME_PRINTABLE: &'static [u8] = b" \"#/:<>?@\\`";
+
+pub static USERNAME_STOPS: [u64; 4] = [
+    0xD400800DFFFFFFFF, 0x8000000110000001, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
 pub static USERNAME: [&'static str; 256] = [
    "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
    "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
@@ -224,6 +344,12 @@ pub static USERNAME: [&'static str; 256] = [
 ];
 
 
+pub static FORM_URLENCODED_PRINTABLE: &'static [u8] = b" !\"#$%&'()+,/:;<=>?@[\\]^`{|}";
+
+pub static FORM_URLENCODED_STOPS: [u64; 4] = [
+    0xFC009BFFFFFFFFFF, 0xB800000178000001, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+];
+
 pub static FORM_URLENCODED: [&'static str; 256] = [
    "%00", "%01", "%02", "%03", "%04", "%05", "%06", "%07",
    "%08", "%09", "%0A", "%0B", "%0C", "%0D", "%0E", "%0F",
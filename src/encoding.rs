@@ -31,9 +31,15 @@ impl EncodingOverride {
         encoding.map(EncodingOverride::from_encoding).unwrap_or_else(EncodingOverride::utf8)
     }
 
+    /// Use the [output encoding](https://encoding.spec.whatwg.org/#get-an-output-encoding)
+    /// of `encoding`: UTF-16 and the replacement encoding can not encode URLs,
+    /// so UTF-8 is used instead.
     pub fn from_encoding(encoding: EncodingRef) -> EncodingOverride {
         EncodingOverride {
-            encoding: if encoding.name() == "utf-8" { None } else { Some(encoding) }
+            encoding: match encoding.name() {
+                "utf-8" | "utf-16be" | "utf-16le" | "replacement" => None,
+                _ => Some(encoding),
+            }
         }
     }
 
@@ -52,6 +58,10 @@ impl EncodingOverride {
         self.encoding.is_none()
     }
 
+    pub fn to_encoding(&self) -> EncodingRef {
+        self.encoding.unwrap_or(self::encoding::all::UTF_8)
+    }
+
     pub fn decode(&self, input: &[u8]) -> String {
         match self.encoding {
             Some(encoding) => encoding.decode(input, DecoderTrap::Replace).unwrap(),
@@ -59,8 +69,14 @@ impl EncodingOverride {
         }
     }
 
+    /// Encode with the *HTML* error mode of the Encoding Standard, as required for query strings
+    /// and form submission: characters that the encoding can not represent are replaced
+    /// by decimal numeric character references such as `&#12354;`.
+    /// These are then percent-encoded like any other bytes,
+    /// as `&%2312354;` in a query string or `%26%2312354%3B` in form data.
     pub fn encode<'a>(&self, input: &'a str) -> Cow<'a, Vec<u8>, [u8]> {
         match self.encoding {
+            // `NcrEscape` is the HTML error mode.
             Some(encoding) => Cow::Owned(
                 encoding.encode(input.as_slice(), EncoderTrap::NcrEscape).unwrap()),
             None => Cow::Borrowed(input.as_bytes()),  // UTF-8
@@ -95,3 +111,110 @@ impl EncodingOverride {
         Cow::Borrowed(input.as_bytes())
     }
 }
+
+
+#[cfg(all(test, feature = "query_encoding"))]
+mod tests {
+    use super::EncodingOverride;
+    use super::encoding::all::{WINDOWS_1252, WINDOWS_31J, UTF_16LE, UTF_8};
+    use super::super::{UrlParser, form_urlencoded};
+
+    #[test]
+    fn unencodable_characters_are_html_escaped() {
+        let windows_1252 = EncodingOverride::from_encoding(WINDOWS_1252);
+        assert_eq!(windows_1252.encode("é€あ😀").as_slice(),
+                   b"\xE9\x80&#12354;&#128512;".as_slice());
+
+        let url = UrlParser::new().query_encoding_override(WINDOWS_31J)
+                                  .parse("http://example.com/?q=あé").unwrap();
+        assert_eq!(url.query, Some("q=%82%A0&%23233;".to_string()));
+
+        let pairs = [("q", "é€あ")];
+        assert_eq!(form_urlencoded::serialize_with_encoding(pairs.iter().map(|&p| p),
+                                                            Some(WINDOWS_1252)).as_slice(),
+                   "q=%E9%80%26%2312354%3B");
+    }
+
+    #[test]
+    fn utf16_output_encoding_is_utf8() {
+        assert!(EncodingOverride::from_encoding(UTF_16LE).is_utf8());
+        let pairs = [("q", "é")];
+        assert_eq!(form_urlencoded::serialize_with_encoding(pairs.iter().map(|&p| p),
+                                                            Some(UTF_16LE)).as_slice(),
+                   "q=%C3%A9");
+    }
+
+    #[test]
+    fn charset_detection() {
+        let input = b"a=%E9&_charset_=windows-1252&b=%E9";
+        let (pairs, encoding) = form_urlencoded::parse_with_charset(input, None, true).unwrap();
+        assert_eq!(encoding.name(), "windows-1252");
+        assert_eq!(pairs[0], ("a".to_string(), "é".to_string()));
+        let (pairs, encoding) = form_urlencoded::parse_with_charset(input, None, false).unwrap();
+        assert_eq!(encoding.name(), UTF_8.name());
+        assert_eq!(pairs[0], ("a".to_string(), "\u{FFFD}".to_string()));
+    }
+
+    #[test]
+    fn utf16_charset_decodes_as_utf8() {
+        // A `_charset_` naming UTF-16 or the replacement encoding decodes as UTF-8,
+        // the encoding that browsers actually submit forms with in that case.
+        for label in ["utf-16le", "utf-16be", "utf-16", "replacement"].iter() {
+            let input = format!("a=%C3%A9&_charset_={}&b=%E2%82%AC", label);
+            let (pairs, encoding) =
+                form_urlencoded::parse_with_charset(input.as_bytes(), None, true).unwrap();
+            assert_eq!(encoding.name(), UTF_8.name());
+            assert_eq!(pairs[0], ("a".to_string(), "é".to_string()));
+            assert_eq!(pairs[2], ("b".to_string(), "€".to_string()));
+        }
+        assert!(EncodingOverride::lookup(b"utf-16le").unwrap().is_utf8());
+    }
+
+    #[test]
+    fn query_with_encoding() {
+        let url = UrlParser::new().query_encoding_override(WINDOWS_1252)
+                                  .parse("http://example.com/?q=é+€").unwrap();
+        assert_eq!(url.query, Some("q=%E9+%80".to_string()));
+        assert_eq!(url.query_pairs_with_encoding(WINDOWS_1252),
+                   Some(vec![("q".to_string(), "é €".to_string())]));
+        assert_eq!(url.lossy_percent_decode_query_with_encoding(WINDOWS_1252),
+                   Some("q=é+€".to_string()));
+        assert_eq!(url.lossy_percent_decode_query(), Some("q=\u{FFFD}+\u{FFFD}".to_string()));
+    }
+
+    #[test]
+    fn stream_with_encoding() {
+        use super::super::form_urlencoded::{parse_stream_with_encoding, Dialect, Limits,
+                                            StreamError};
+
+        let input = b"a=%E9+x;b=%80";
+        let pairs = parse_stream_with_encoding(&input[..], Dialect::html4(), Some(WINDOWS_1252),
+                                               Limits::unlimited())
+            .map(|pair| pair.unwrap()).collect::<Vec<_>>();
+        assert_eq!(pairs, vec![("a".to_string(), "é x".to_string()),
+                               ("b".to_string(), "€".to_string())]);
+        let mut stream = parse_stream_with_encoding(&b"a=\xE9"[..], Dialect::whatwg(),
+                                                    Some(WINDOWS_1252), Limits::unlimited());
+        match stream.next() {
+            Some(Err(StreamError::InvalidEncoding)) => (),
+            _ => panic!("expected InvalidEncoding"),
+        }
+    }
+
+    #[test]
+    fn query_as_with_encoding() {
+        #[derive(RustcEncodable, RustcDecodable, PartialEq, Debug)]
+        struct Search {
+            q: String,
+        }
+
+        let mut url = UrlParser::new().query_encoding_override(WINDOWS_1252)
+                                      .parse("http://example.com/?q=caf\u{E9}").unwrap();
+        assert_eq!(url.query_as_with_encoding(WINDOWS_1252),
+                   Ok(Search { q: "caf\u{E9}".to_string() }));
+        assert_eq!(url.query_as(), Ok(Search { q: "caf\u{FFFD}".to_string() }));
+        url.set_query_as_with_encoding(&Search { q: "\u{E9}t\u{E9}".to_string() }, WINDOWS_1252)
+           .unwrap();
+        assert_eq!(url.query, Some("q=%E9t%E9".to_string()));
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Guess the URL intended by free-form user input,
+//! such as what is typed in the address bar (a.k.a. omnibox) of a web browser.
+//!
+//! Users type things like `example.com/foo`, `localhost:3000`, `~/file.txt`,
+//! `C:\dir\file` or `user@host` that are not valid absolute URLs
+//! but are not ambiguous either.
+//!
+//! ```
+//! use url::fixup::{fixup_user_input, FixupReason};
+//!
+//! let fixup = fixup_user_input("  example.com/foo ").unwrap();
+//! assert_eq!(fixup.url.serialize(), "http://example.com/foo".to_string());
+//! assert_eq!(fixup.reason, FixupReason::DefaultScheme);
+//! ```
+
+use std::ascii::AsciiExt;
+use std::old_path;
+use super::{Url, SchemeType, whatwg_scheme_type_mapper};
+use parser::{self, Context};
+
+
+/// The result of fixing up user input.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Fixup {
+    /// The URL that was probably intended.
+    pub url: Url,
+
+    /// How the input was interpreted.
+    pub reason: FixupReason,
+
+    /// How likely it is that `url` is what was intended.
+    pub confidence: Confidence,
+}
+
+
+/// How user input was interpreted.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum FixupReason {
+    /// The input was an absolute URL in a well-known scheme, and was parsed as-is.
+    AbsoluteUrl,
+
+    /// The input was an absolute URL in a scheme that is not well-known.
+    /// It may have been meant as something else, such as `host:path`.
+    UnknownScheme,
+
+    /// The input looked like a host name, optionally followed by a path,
+    /// and the default scheme was prepended.
+    DefaultScheme,
+
+    /// The input looked like `host:port`, optionally followed by a path,
+    /// rather than like a scheme. The default scheme was prepended.
+    HostAndPort,
+
+    /// The input looked like `user@host`, and the default scheme was prepended.
+    UserAndHost,
+
+    /// The input was an absolute local file name, converted with `Url::from_file_path`.
+    FilePath,
+
+    /// The input was a file name starting with `~`,
+    /// resolved against the home directory and converted with `Url::from_file_path`.
+    HomeDirectoryPath,
+}
+
+
+/// How likely it is that a fixed up URL is what the user intended.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+
+/// A method-chaining API to provide a set of optional parameters for user input fixup.
+pub struct UserInputFixer<'a> {
+    default_scheme: &'a str,
+    home_directory: Option<&'a old_path::posix::Path>,
+}
+
+
+impl<'a> UserInputFixer<'a> {
+    /// Return a new UserInputFixer with default parameters.
+    #[inline]
+    pub fn new() -> UserInputFixer<'a> {
+        UserInputFixer {
+            default_scheme: "http",
+            home_directory: None,
+        }
+    }
+
+    /// Set the scheme prepended to inputs that do not have one,
+    /// and return the `UserInputFixer`.
+    /// The default is `"http"`.
+    #[inline]
+    pub fn default_scheme<'b>(&'b mut self, value: &'a str) -> &'b mut UserInputFixer<'a> {
+        self.default_scheme = value;
+        self
+    }
+
+    /// Set the directory that `~` refers to, and return the `UserInputFixer`.
+    /// The default is no home directory, so that inputs starting with `~` are not fixed up.
+    #[inline]
+    pub fn home_directory<'b>(&'b mut self, value: &'a old_path::posix::Path)
+                              -> &'b mut UserInputFixer<'a> {
+        self.home_directory = Some(value);
+        self
+    }
+
+    /// Guess the URL intended by `input`, with all the parameters previously set.
+    ///
+    /// Leading and trailing whitespace is ignored.
+    /// Return `None` if no URL could be made from the input.
+    pub fn fixup(&self, input: &str) -> Option<Fixup> {
+        let input = input.trim();
+        if input.is_empty() || input.starts_with("?") {
+            return None
+        }
+
+        if input == "~" || input.starts_with("~/") {
+            return self.home_directory.and_then(|home| {
+                let path = if input == "~" { home.clone() } else { home.join(&input[2..]) };
+                file_path_fixup(Url::from_file_path(&path), FixupReason::HomeDirectoryPath)
+            })
+        }
+        if is_windows_drive_path(input) {
+            return old_path::windows::Path::new_opt(input).and_then(|path| {
+                file_path_fixup(Url::from_file_path(&path), FixupReason::FilePath)
+            })
+        }
+        if input.starts_with("//") {
+            // Scheme-relative
+            return self.with_default_scheme(&input[2..], FixupReason::DefaultScheme)
+        }
+        if input.starts_with("/") {
+            return old_path::posix::Path::new_opt(input).and_then(|path| {
+                file_path_fixup(Url::from_file_path(&path), FixupReason::FilePath)
+            })
+        }
+
+        if let Some((scheme, remaining)) = parser::parse_scheme(input, Context::UrlParser) {
+            if is_well_known_scheme(scheme.as_slice()) {
+                return Url::parse(input).ok().map(|url| Fixup {
+                    url: url,
+                    reason: FixupReason::AbsoluteUrl,
+                    confidence: Confidence::High,
+                })
+            }
+            if starts_with_port(remaining) {
+                return self.with_default_scheme(input, FixupReason::HostAndPort)
+            }
+            let confidence = if remaining.starts_with("//") {
+                Confidence::Medium
+            } else {
+                Confidence::Low
+            };
+            return Url::parse(input).ok().map(|url| Fixup {
+                url: url,
+                reason: FixupReason::UnknownScheme,
+                confidence: confidence,
+            })
+        }
+
+        let authority = &input[..input.find(['/', '?', '#'].as_slice()).unwrap_or(input.len())];
+        let reason = if authority.contains_char('@') {
+            FixupReason::UserAndHost
+        } else {
+            FixupReason::DefaultScheme
+        };
+        self.with_default_scheme(input, reason)
+    }
+
+    /// Return whether `input` is more likely to be a search query than an URL.
+    ///
+    /// This is a heuristic: inputs that contain whitespace, that start with `?`,
+    /// or that are a single word with no `.`, `:`, `/` or `@`
+    /// (other than `localhost`) are considered search queries.
+    pub fn is_probably_search_query(&self, input: &str) -> bool {
+        let input = input.trim();
+        if input.starts_with("?") {
+            return true
+        }
+        match self.fixup(input) {
+            None => !input.is_empty(),
+            Some(fixup) => match fixup.reason {
+                FixupReason::AbsoluteUrl | FixupReason::FilePath |
+                FixupReason::HomeDirectoryPath | FixupReason::HostAndPort => false,
+                FixupReason::UnknownScheme => input.contains_char(' '),
+                FixupReason::DefaultScheme | FixupReason::UserAndHost => {
+                    fixup.confidence == Confidence::Low
+                }
+            }
+        }
+    }
+
+    fn with_default_scheme(&self, input: &str, reason: FixupReason) -> Option<Fixup> {
+        let authority = &input[..input.find(['/', '?', '#'].as_slice()).unwrap_or(input.len())];
+        if authority.is_empty() || authority.chars().any(|c| c.is_whitespace()) {
+            return None
+        }
+        let host = match authority.rfind('@') {
+            Some(at) => &authority[at + 1..],
+            None => authority,
+        };
+        let host = if host.starts_with("[") {
+            host
+        } else {
+            &host[..host.find(':').unwrap_or(host.len())]
+        };
+        let confidence = if host.starts_with("[") || host.contains_char('.')
+                             || host.eq_ignore_ascii_case("localhost") {
+            if reason == FixupReason::UserAndHost { Confidence::Medium } else { Confidence::High }
+        } else if reason == FixupReason::HostAndPort {
+            Confidence::Medium
+        } else {
+            Confidence::Low
+        };
+        Url::parse(format!("{}://{}", self.default_scheme, input).as_slice()).ok().map(|url| {
+            Fixup { url: url, reason: reason, confidence: confidence }
+        })
+    }
+}
+
+
+/// Guess the URL intended by `input`, with the default `UserInputFixer` parameters.
+///
+/// Return `None` if no URL could be made from the input.
+#[inline]
+pub fn fixup_user_input(input: &str) -> Option<Fixup> {
+    UserInputFixer::new().fixup(input)
+}
+
+
+fn file_path_fixup(url: Result<Url, ()>, reason: FixupReason) -> Option<Fixup> {
+    url.ok().map(|url| Fixup { url: url, reason: reason, confidence: Confidence::Medium })
+}
+
+
+fn is_well_known_scheme(scheme: &str) -> bool {
+    whatwg_scheme_type_mapper(scheme) != SchemeType::NonRelative || matches!(scheme,
+        "about" | "blob" | "data" | "javascript" | "mailto" | "tel" | "urn" | "view-source")
+}
+
+
+/// Whether `input` starts with ASCII digits up to the end of the authority,
+/// like the port number in `localhost:3000/foo`.
+fn starts_with_port(input: &str) -> bool {
+    let end = input.find(['/', '?', '#'].as_slice()).unwrap_or(input.len());
+    end > 0 && input[..end].bytes().all(|b| matches!(b, b'0'...b'9'))
+}
+
+
+/// `C:\dir\file` or `C:/dir/file`
+fn is_windows_drive_path(input: &str) -> bool {
+    input.len() >= 3
+    && parser::starts_with_ascii_alpha(input)
+    && input.as_bytes()[1] == b':'
+    && matches!(input.as_bytes()[2], b'\\' | b'/')
+}
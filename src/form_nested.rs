@@ -0,0 +1,425 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Nested parameters in `application/x-www-form-urlencoded` data,
+//! with the bracket notation used by Rails (Rack) and PHP:
+//! `user[name]=x&user[tags][]=a&user[tags][]=b`.
+//!
+//! Names and values are percent-decoded with `form_urlencoded::parse` before brackets are
+//! interpreted, so `user%5Bname%5D=x` is the same as `user[name]=x`.
+//!
+//! ```
+//! use url::form_nested::{parse, serialize, Options, Value};
+//!
+//! let params = parse(b"user[name]=x&user[tags][]=a&user[tags][]=b", &Options::new()).unwrap();
+//! assert_eq!(params, vec![("user".to_string(), Value::Map(vec![
+//!     ("name".to_string(), Value::String("x".to_string())),
+//!     ("tags".to_string(), Value::Array(vec![
+//!         Value::String("a".to_string()),
+//!         Value::String("b".to_string()),
+//!     ])),
+//! ]))]);
+//! assert_eq!(serialize(params.as_slice(), &Options::new()).as_slice(),
+//!            "user%5Bname%5D=x&user%5Btags%5D%5B%5D=a&user%5Btags%5D%5B%5D=b");
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt::{self, Formatter};
+use form_urlencoded::{self, Limits};
+use parser::ParseError;
+
+
+/// A nested parameter value.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Value {
+    String(String),
+    Array(Vec<Value>),
+    /// (key, value) pairs, in the order the keys first appear in the input.
+    Map(Vec<(String, Value)>),
+}
+
+
+impl Value {
+    /// If this is a string, return it.
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref value) => Some(value.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// If this is an array, return its items.
+    #[inline]
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref items) => Some(items.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// If this is a map, return the value for `key`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Map(ref entries) => get(entries.as_slice(), key),
+            _ => None,
+        }
+    }
+}
+
+
+/// Return the value for `key` in (key, value) pairs, such as returned by `parse`.
+pub fn get<'a>(entries: &'a [(String, Value)], key: &str) -> Option<&'a Value> {
+    entries.iter().find(|&&(ref k, _)| k.as_slice() == key).map(|&(_, ref value)| value)
+}
+
+
+/// How array items are written.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ArrayStyle {
+    /// `tags[]=a&tags[]=b`, as in Rails.
+    EmptyBrackets,
+
+    /// `tags[0]=a&tags[1]=b`, as in PHP’s `http_build_query`.
+    /// This is the only style where serializing then parsing
+    /// arrays of arrays or of maps always gives the same value.
+    Indexed,
+
+    /// `tags=a&tags=b`. When parsing, repeating a name without brackets makes an array
+    /// instead of keeping only the last value.
+    /// When serializing, items that are not strings are written with `Indexed`.
+    RepeatedKey,
+}
+
+
+/// Parameters for `parse` and `serialize`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Options {
+    /// The maximum number of bracketed keys in a name:
+    /// `a[b][c]` has a depth of 2. Exceeding it is a `TooDeep` error.
+    pub max_depth: usize,
+
+    /// The maximum number of (name, value) pairs. Exceeding it is a `TooManyParams` error,
+    /// returned before any pair is decoded. `None` means no limit.
+    pub max_params: Option<usize>,
+
+    /// How array items are written.
+    pub array_style: ArrayStyle,
+}
+
+
+impl Options {
+    /// A depth of 32, 1000 params, and `ArrayStyle::EmptyBrackets`.
+    pub fn new() -> Options {
+        Options {
+            max_depth: 32,
+            max_params: Some(1000),
+            array_style: ArrayStyle::EmptyBrackets,
+        }
+    }
+}
+
+
+/// Errors that can occur when parsing nested parameters.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Error {
+    /// There are more (name, value) pairs than `Options::max_params`.
+    TooManyParams,
+
+    /// This name has more bracketed keys than `Options::max_depth`.
+    TooDeep(String),
+
+    /// This name is used with different types,
+    /// such as `a=1&a[b]=2` or `a[]=1&a[b]=2`.
+    Conflict(String),
+}
+
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::TooManyParams => "too many parameters",
+            Error::TooDeep(_) => "parameter nested too deeply",
+            Error::Conflict(_) => "conflicting parameter types",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            Error::TooDeep(ref name) => write!(fmt, "parameter `{}` nested too deeply", name),
+            Error::Conflict(ref name) => write!(fmt, "conflicting types for parameter `{}`", name),
+            _ => self.description().fmt(fmt),
+        }
+    }
+}
+
+
+/// Parse a byte string in the `application/x-www-form-urlencoded` format
+/// into nested (key, value) pairs.
+///
+/// * `a[]=x` appends to an array.
+/// * `a[0]=x` sets an array item. Indices past the end append, so that a huge index
+///   does not allocate a huge array.
+/// * `a[b]=x` sets a map value. Repeating it keeps the last value,
+///   unless `options.array_style` is `RepeatedKey`.
+/// * `a[][b]=x&a[][c]=y` adds to the last map in the array while its keys are new,
+///   like Rack does.
+/// * Names that start with `[` or have an unclosed `[` are used as plain keys,
+///   and anything after the last `]` is ignored. Pairs with an empty name are ignored.
+pub fn parse(input: &[u8], options: &Options) -> Result<Vec<(String, Value)>, Error> {
+    // Count pairs before decoding any of them, so that a huge input is rejected early.
+    let limits = Limits { max_pairs: options.max_params, ..Limits::unlimited() };
+    let pairs = match form_urlencoded::parse_with_limits(input, limits) {
+        Ok(pairs) => pairs,
+        Err(ParseError::TooManyFormPairs) => return Err(Error::TooManyParams),
+        Err(_) => unreachable!(),
+    };
+    let mut root = Value::Map(Vec::new());
+    for (name, value) in pairs.into_iter() {
+        if name.is_empty() {
+            continue
+        }
+        let keys = split_name(name.as_slice());
+        if keys.len() - 1 > options.max_depth {
+            return Err(Error::TooDeep(name.clone()))
+        }
+        let repeat = options.array_style == ArrayStyle::RepeatedKey;
+        if insert(&mut root, keys.as_slice(), value, repeat).is_err() {
+            return Err(Error::Conflict(name.clone()))
+        }
+    }
+    match root {
+        Value::Map(entries) => Ok(entries),
+        _ => unreachable!(),
+    }
+}
+
+
+/// Serialize nested (key, value) pairs into a string in the
+/// `application/x-www-form-urlencoded` format.
+///
+/// Empty arrays and maps are omitted, since they have no representation.
+pub fn serialize(params: &[(String, Value)], options: &Options) -> String {
+    let mut pairs = Vec::new();
+    for &(ref key, ref value) in params.iter() {
+        flatten(key.clone(), value, options.array_style, &mut pairs);
+    }
+    form_urlencoded::serialize_owned(pairs.as_slice())
+}
+
+
+fn flatten(name: String, value: &Value, style: ArrayStyle, pairs: &mut Vec<(String, String)>) {
+    match *value {
+        Value::String(ref value) => pairs.push((name, value.clone())),
+        Value::Map(ref entries) => {
+            for &(ref key, ref value) in entries.iter() {
+                flatten(format!("{}[{}]", name, key), value, style, pairs)
+            }
+        },
+        Value::Array(ref items) => {
+            for (i, item) in items.iter().enumerate() {
+                let item_name = match (style, item) {
+                    (ArrayStyle::EmptyBrackets, _) => format!("{}[]", name),
+                    (ArrayStyle::RepeatedKey, &Value::String(_)) => name.clone(),
+                    _ => format!("{}[{}]", name, i),
+                };
+                flatten(item_name, item, style, pairs)
+            }
+        },
+    }
+}
+
+
+/// Split `a[b][]` into `["a", "b", ""]`.
+fn split_name(name: &str) -> Vec<&str> {
+    let open = match name.find('[') {
+        Some(open) if open > 0 => open,
+        _ => return vec![name],
+    };
+    let mut keys = vec![&name[..open]];
+    let mut remaining = &name[open..];
+    while remaining.starts_with("[") {
+        match remaining.find(']') {
+            Some(close) => {
+                keys.push(&remaining[1..close]);
+                remaining = &remaining[close + 1..];
+            },
+            None => break,
+        }
+    }
+    if keys.len() == 1 { vec![name] } else { keys }
+}
+
+
+fn parse_index(key: &str) -> Option<usize> {
+    // Limit the length to avoid overflow, and reject leading zeros.
+    if key.is_empty() || key.len() > 9 || (key.len() > 1 && key.starts_with("0"))
+            || !key.bytes().all(|b| matches!(b, b'0'...b'9')) {
+        return None
+    }
+    key.parse().ok()
+}
+
+
+/// An empty container for the key after `key`.
+fn new_container(next_key: &str) -> Value {
+    if next_key.is_empty() || parse_index(next_key).is_some() {
+        Value::Array(Vec::new())
+    } else {
+        Value::Map(Vec::new())
+    }
+}
+
+
+/// Set `value` at the path `keys` in the container `target`.
+/// Return `Err` on type conflicts.
+fn insert(target: &mut Value, keys: &[&str], value: String, repeat: bool) -> Result<(), ()> {
+    let key = keys[0];
+    let is_last = keys.len() == 1;
+    match *target {
+        Value::Map(ref mut entries) => {
+            if key.is_empty() {
+                return Err(())
+            }
+            let position = entries.iter().position(|&(ref k, _)| k.as_slice() == key);
+            if is_last {
+                let new_value = Value::String(value);
+                match position {
+                    None => entries.push((key.to_string(), new_value)),
+                    Some(i) => {
+                        let existing = &mut entries[i].1;
+                        if !repeat {
+                            match *existing {
+                                Value::String(_) => *existing = new_value,
+                                _ => return Err(()),
+                            }
+                        } else {
+                            let first = match *existing {
+                                Value::String(ref first) => first.clone(),
+                                Value::Array(ref mut items) => {
+                                    items.push(new_value);
+                                    return Ok(())
+                                },
+                                Value::Map(_) => return Err(()),
+                            };
+                            *existing = Value::Array(vec![Value::String(first), new_value])
+                        }
+                    },
+                }
+                return Ok(())
+            }
+            let i = match position {
+                Some(i) => i,
+                None => {
+                    entries.push((key.to_string(), new_container(keys[1])));
+                    entries.len() - 1
+                },
+            };
+            insert(&mut entries[i].1, &keys[1..], value, repeat)
+        },
+        Value::Array(ref mut items) => {
+            let i = if key.is_empty() {
+                let reuse = !is_last && match items.last() {
+                    Some(item @ &Value::Map(_)) => !contains_path(item, &keys[1..]),
+                    _ => false,
+                };
+                if !reuse {
+                    items.push(if is_last { Value::String(String::new()) }
+                               else { new_container(keys[1]) });
+                }
+                items.len() - 1
+            } else {
+                match parse_index(key) {
+                    Some(i) if i < items.len() => i,
+                    Some(_) => {
+                        items.push(if is_last { Value::String(String::new()) }
+                                   else { new_container(keys[1]) });
+                        items.len() - 1
+                    },
+                    None => return Err(()),
+                }
+            };
+            if is_last {
+                if items[i].as_str().is_none() {
+                    return Err(())
+                }
+                items[i] = Value::String(value);
+                Ok(())
+            } else {
+                insert(&mut items[i], &keys[1..], value, repeat)
+            }
+        },
+        Value::String(_) => Err(()),
+    }
+}
+
+
+/// Whether there is already a value at the path `keys` in `value`.
+fn contains_path(value: &Value, keys: &[&str]) -> bool {
+    match *value {
+        Value::Map(ref entries) => match get(entries.as_slice(), keys[0]) {
+            Some(child) => keys.len() == 1 || contains_path(child, &keys[1..]),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+
+#[test]
+fn test_form_nested() {
+    let options = Options::new();
+    let params = parse(b"items[][name]=a&items[][price]=1&items[][name]=b&x[1]=p&x[5]=q&x[0]=r",
+                       &options).unwrap();
+    let items = get(params.as_slice(), "items").and_then(|v| v.as_array()).unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].get("price").and_then(|v| v.as_str()), Some("1"));
+    assert_eq!(items[1].get("name").and_then(|v| v.as_str()), Some("b"));
+    assert_eq!(get(params.as_slice(), "x"), Some(&Value::Array(vec![
+        Value::String("r".to_string()), Value::String("q".to_string()),
+    ])));
+
+    assert_eq!(parse(b"a=1&a[b]=2", &options), Err(Error::Conflict("a[b]".to_string())));
+    assert_eq!(parse(b"a[]=1&a[b]=2", &options), Err(Error::Conflict("a[b]".to_string())));
+    assert_eq!(parse(b"a=1&a=2", &options),
+               Ok(vec![("a".to_string(), Value::String("2".to_string()))]));
+    assert_eq!(parse(b"[a]=1&b[c=2&d[e]f=3", &options), Ok(vec![
+        ("[a]".to_string(), Value::String("1".to_string())),
+        ("b[c".to_string(), Value::String("2".to_string())),
+        ("d".to_string(), Value::Map(vec![("e".to_string(), Value::String("3".to_string()))])),
+    ]));
+
+    let limited = Options { max_depth: 2, max_params: Some(2), ..options };
+    assert!(parse(b"a[b][c]=1", &limited).is_ok());
+    assert_eq!(parse(b"a[b][c][d]=1", &limited), Err(Error::TooDeep("a[b][c][d]".to_string())));
+    assert_eq!(parse(b"a=1&b=2&c=3", &limited), Err(Error::TooManyParams));
+
+    // A million pairs: only the first 1001 are counted, none is decoded.
+    let mut huge = String::new();
+    for _ in 0..1_000_000 {
+        huge.push_str("a[]=1&");
+    }
+    assert_eq!(parse(huge.as_bytes(), &options), Err(Error::TooManyParams));
+
+    let repeated = Options { array_style: ArrayStyle::RepeatedKey, ..options };
+    let params = parse(b"t=a&t=b&m[k]=1", &repeated).unwrap();
+    assert_eq!(serialize(params.as_slice(), &repeated).as_slice(), "t=a&t=b&m%5Bk%5D=1");
+
+    let indexed = Options { array_style: ArrayStyle::Indexed, ..options };
+    let nested = vec![("a".to_string(), Value::Array(vec![
+        Value::Array(vec![Value::String("1".to_string()), Value::String("2".to_string())]),
+        Value::Map(vec![]),
+    ]))];
+    let serialized = serialize(nested.as_slice(), &indexed);
+    assert_eq!(serialized.as_slice(), "a%5B0%5D%5B0%5D=1&a%5B0%5D%5B1%5D=2");
+    assert_eq!(parse(serialized.as_bytes(), &indexed), Ok(vec![("a".to_string(), Value::Array(vec![
+        Value::Array(vec![Value::String("1".to_string()), Value::String("2".to_string())]),
+    ]))]));
+}
@@ -0,0 +1,668 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Convert between `application/x-www-form-urlencoded` strings (such as query strings
+//! and HTML form submissions) and types that implement `rustc_serialize`’s
+//! `Encodable` and `Decodable`, usually with `#[derive(RustcEncodable, RustcDecodable)]`.
+//!
+//! The top-level value is a struct or a map. Each field is one or more (name, value) pairs:
+//!
+//! * Strings, numbers, booleans and characters are a single pair.
+//! * `Vec<T>` fields are one pair per item, with a repeated name.
+//! * `Option<T>` fields are no pair when `None`.
+//! * Enums with no data are the name of their variant.
+//! * Newtype structs such as `struct Id(u32)` are the value they wrap.
+//!
+//! Nested structs and maps, tuples and enum variants with data have no representation
+//! as (name, value) pairs, and are errors.
+//!
+//! ```
+//! extern crate "rustc-serialize" as rustc_serialize;
+//! extern crate url;
+//! use url::form_serialize;
+//!
+//! #[derive(RustcEncodable, RustcDecodable, PartialEq, Debug)]
+//! struct Search {
+//!     q: String,
+//!     page: Option<u32>,
+//!     tags: Vec<String>,
+//! }
+//!
+//! fn main() {
+//!     let search: Search = form_serialize::decode(b"q=rust+url&tags=a&tags=b").unwrap();
+//!     assert_eq!(search, Search {
+//!         q: "rust url".to_string(),
+//!         page: None,
+//!         tags: vec!["a".to_string(), "b".to_string()],
+//!     });
+//!     assert_eq!(form_serialize::encode(&search).unwrap().as_slice(), "q=rust+url&tags=a&tags=b");
+//! }
+//! ```
+
+use std::error::Error;
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
+use rustc_serialize::{self, Encodable, Decodable};
+use form_urlencoded;
+
+
+/// Errors that can occur when encoding a value as (name, value) pairs.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EncodeError {
+    /// The top-level value is not a struct or a map.
+    TopLevelNotAStructOrMap,
+
+    /// A map key is not a string, a number, a boolean or a character.
+    InvalidKey,
+
+    /// A value that can not be represented as (name, value) pairs,
+    /// such as a nested struct, a tuple or an enum variant with data.
+    Unsupported(&'static str),
+}
+
+
+impl Error for EncodeError {
+    fn description(&self) -> &str {
+        match *self {
+            EncodeError::TopLevelNotAStructOrMap => "the top-level value is not a struct or a map",
+            EncodeError::InvalidKey => "invalid map key",
+            EncodeError::Unsupported(_) => "unsupported value",
+        }
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            EncodeError::Unsupported(what) => write!(fmt, "unsupported value: {}", what),
+            _ => self.description().fmt(fmt),
+        }
+    }
+}
+
+
+/// Errors that can occur when decoding (name, value) pairs into a value.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum DecodeError {
+    /// The top-level type is not a struct or a map.
+    TopLevelNotAStructOrMap,
+
+    /// There is no pair with this name for a field that is not an `Option` or a `Vec`.
+    MissingField(String),
+
+    /// A value could not be parsed as the type of its field.
+    InvalidValue {
+        name: String,
+        value: String,
+        /// What was expected, such as `"unsigned integer"`.
+        expected: &'static str,
+    },
+
+    /// A value is not the name of any of the variants of its enum field.
+    UnknownVariant {
+        name: String,
+        value: String,
+    },
+
+    /// A type that can not be represented as (name, value) pairs,
+    /// such as a nested struct, a tuple or an enum variant with data.
+    Unsupported(&'static str),
+
+    /// The input could not be decoded with the given character encoding.
+    InvalidEncoding,
+
+    /// An error raised by a `Decodable` implementation.
+    Custom(String),
+}
+
+
+impl Error for DecodeError {
+    fn description(&self) -> &str {
+        match *self {
+            DecodeError::TopLevelNotAStructOrMap => "the top-level type is not a struct or a map",
+            DecodeError::MissingField(_) => "missing field",
+            DecodeError::InvalidValue { .. } => "invalid value",
+            DecodeError::UnknownVariant { .. } => "unknown enum variant",
+            DecodeError::Unsupported(_) => "unsupported type",
+            DecodeError::InvalidEncoding => "invalid input for the character encoding",
+            DecodeError::Custom(ref message) => message.as_slice(),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::MissingField(ref name) => write!(fmt, "missing field `{}`", name),
+            DecodeError::InvalidValue { ref name, ref value, expected } => {
+                write!(fmt, "invalid value {:?} for field `{}`: expected {}", value, name, expected)
+            },
+            DecodeError::UnknownVariant { ref name, ref value } => {
+                write!(fmt, "unknown variant {:?} for field `{}`", value, name)
+            },
+            DecodeError::Unsupported(what) => write!(fmt, "unsupported type: {}", what),
+            _ => self.description().fmt(fmt),
+        }
+    }
+}
+
+
+/// Encode a value as a string in the `application/x-www-form-urlencoded` format.
+#[inline]
+pub fn encode<T: Encodable>(value: &T) -> Result<String, EncodeError> {
+    encode_pairs(value).map(|pairs| form_urlencoded::serialize_owned(pairs.as_slice()))
+}
+
+
+/// Encode a value as a string in the `application/x-www-form-urlencoded` format.
+///
+/// This function is only available if the `query_encoding` Cargo feature is enabled.
+///
+/// Arguments:
+///
+/// * `encoding_override`: The character encoding each name and values is encoded as
+///    before percent-encoding. Defaults to UTF-8.
+#[cfg(feature = "query_encoding")]
+#[inline]
+pub fn encode_with_encoding<T: Encodable>(value: &T,
+                                          encoding_override: Option<::encoding::EncodingRef>)
+                                          -> Result<String, EncodeError> {
+    encode_pairs(value).map(|pairs| form_urlencoded::serialize_with_encoding(
+        pairs.iter().map(|&(ref n, ref v)| (n.as_slice(), v.as_slice())), encoding_override))
+}
+
+
+/// Encode a value as a vector of (name, value) pairs.
+pub fn encode_pairs<T: Encodable>(value: &T) -> Result<Vec<(String, String)>, EncodeError> {
+    let mut encoder = Encoder::new();
+    try!(value.encode(&mut encoder));
+    Ok(encoder.into_pairs())
+}
+
+
+/// Decode a byte string in the `application/x-www-form-urlencoded` format into a value.
+///
+/// Use `decode(input.as_bytes())` to decode a `&str` string.
+#[inline]
+pub fn decode<T: Decodable>(input: &[u8]) -> Result<T, DecodeError> {
+    decode_pairs(form_urlencoded::parse(input))
+}
+
+
+/// Decode a byte string in the `application/x-www-form-urlencoded` format into a value.
+///
+/// This function is only available if the `query_encoding` Cargo feature is enabled.
+///
+/// Arguments:
+///
+/// * `encoding_override`: The character encoding each name and values is decoded as
+///    after percent-decoding. Defaults to UTF-8.
+/// * `use_charset`: The *use _charset_ flag*. If in doubt, set to `false`.
+#[cfg(feature = "query_encoding")]
+#[inline]
+pub fn decode_with_encoding<T: Decodable>(input: &[u8],
+                                          encoding_override: Option<::encoding::EncodingRef>,
+                                          use_charset: bool)
+                                          -> Result<T, DecodeError> {
+    match form_urlencoded::parse_with_encoding(input, encoding_override, use_charset) {
+        Some(pairs) => decode_pairs(pairs),
+        None => Err(DecodeError::InvalidEncoding),
+    }
+}
+
+
+/// Decode a vector of (name, value) pairs into a value.
+#[inline]
+pub fn decode_pairs<T: Decodable>(pairs: Vec<(String, String)>) -> Result<T, DecodeError> {
+    Decodable::decode(&mut Decoder::new(pairs))
+}
+
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum State {
+    /// Before the top-level struct or map.
+    Start,
+    /// Between fields of the top-level struct or map.
+    Fields,
+    /// In a map key.
+    Key,
+    /// In the value of a field.
+    Value,
+    /// In an item of a sequence value.
+    SequenceItem,
+}
+
+
+/// A `rustc_serialize::Encoder` that produces (name, value) pairs.
+pub struct Encoder {
+    pairs: Vec<(String, String)>,
+    name: String,
+    state: State,
+}
+
+
+impl Encoder {
+    /// Return a new encoder with no pair.
+    #[inline]
+    pub fn new() -> Encoder {
+        Encoder { pairs: Vec::new(), name: String::new(), state: State::Start }
+    }
+
+    /// Return the pairs encoded so far.
+    #[inline]
+    pub fn into_pairs(self) -> Vec<(String, String)> {
+        self.pairs
+    }
+
+    fn emit_value(&mut self, value: String) -> Result<(), EncodeError> {
+        match self.state {
+            State::Start | State::Fields => Err(EncodeError::TopLevelNotAStructOrMap),
+            State::Key => {
+                self.name = value;
+                Ok(())
+            },
+            State::Value | State::SequenceItem => {
+                self.pairs.push((self.name.clone(), value));
+                Ok(())
+            },
+        }
+    }
+
+    fn emit_container<F>(&mut self, what: &'static str, f: F) -> Result<(), EncodeError>
+                         where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        match self.state {
+            State::Start => {
+                self.state = State::Fields;
+                f(self)
+            },
+            State::Key => Err(EncodeError::InvalidKey),
+            _ => Err(EncodeError::Unsupported(what)),
+        }
+    }
+
+    fn emit_in_state<F>(&mut self, state: State, f: F) -> Result<(), EncodeError>
+                        where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        let previous = self.state;
+        self.state = state;
+        let result = f(self);
+        self.state = previous;
+        result
+    }
+}
+
+
+impl rustc_serialize::Encoder for Encoder {
+    type Error = EncodeError;
+
+    fn emit_nil(&mut self) -> Result<(), EncodeError> { self.emit_value(String::new()) }
+    fn emit_usize(&mut self, v: usize) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_u64(&mut self, v: u64) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_u32(&mut self, v: u32) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_u16(&mut self, v: u16) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_u8(&mut self, v: u8) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_isize(&mut self, v: isize) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_i64(&mut self, v: i64) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_i32(&mut self, v: i32) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_i16(&mut self, v: i16) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_i8(&mut self, v: i8) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_bool(&mut self, v: bool) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_f64(&mut self, v: f64) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_f32(&mut self, v: f32) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_char(&mut self, v: char) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+    fn emit_str(&mut self, v: &str) -> Result<(), EncodeError> { self.emit_value(v.to_string()) }
+
+    fn emit_enum<F>(&mut self, _name: &str, f: F) -> Result<(), EncodeError>
+                    where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_enum_variant<F>(&mut self, v_name: &str, _v_id: usize, len: usize, _f: F)
+                            -> Result<(), EncodeError>
+                            where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        if len == 0 {
+            self.emit_value(v_name.to_string())
+        } else {
+            Err(EncodeError::Unsupported("enum variant with data"))
+        }
+    }
+
+    fn emit_enum_variant_arg<F>(&mut self, _a_idx: usize, _f: F) -> Result<(), EncodeError>
+                                where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        Err(EncodeError::Unsupported("enum variant with data"))
+    }
+
+    fn emit_enum_struct_variant<F>(&mut self, v_name: &str, v_id: usize, len: usize, f: F)
+                                   -> Result<(), EncodeError>
+                                   where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        self.emit_enum_variant(v_name, v_id, len, f)
+    }
+
+    fn emit_enum_struct_variant_field<F>(&mut self, _f_name: &str, _f_idx: usize, _f: F)
+                                         -> Result<(), EncodeError>
+                                         where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        Err(EncodeError::Unsupported("enum variant with data"))
+    }
+
+    fn emit_struct<F>(&mut self, _name: &str, _len: usize, f: F) -> Result<(), EncodeError>
+                      where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        self.emit_container("nested struct", f)
+    }
+
+    fn emit_struct_field<F>(&mut self, f_name: &str, _f_idx: usize, f: F)
+                            -> Result<(), EncodeError>
+                            where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        self.name = f_name.to_string();
+        self.emit_in_state(State::Value, f)
+    }
+
+    fn emit_tuple<F>(&mut self, _len: usize, _f: F) -> Result<(), EncodeError>
+                     where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        Err(EncodeError::Unsupported("tuple"))
+    }
+
+    fn emit_tuple_arg<F>(&mut self, _idx: usize, _f: F) -> Result<(), EncodeError>
+                         where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        Err(EncodeError::Unsupported("tuple"))
+    }
+
+    fn emit_tuple_struct<F>(&mut self, _name: &str, len: usize, f: F) -> Result<(), EncodeError>
+                            where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        // Newtype structs are encoded as the value they wrap.
+        if len == 1 { f(self) } else { Err(EncodeError::Unsupported("tuple struct")) }
+    }
+
+    fn emit_tuple_struct_arg<F>(&mut self, _f_idx: usize, f: F) -> Result<(), EncodeError>
+                                where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_option<F>(&mut self, f: F) -> Result<(), EncodeError>
+                      where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_option_none(&mut self) -> Result<(), EncodeError> {
+        match self.state {
+            State::Start | State::Fields => Err(EncodeError::TopLevelNotAStructOrMap),
+            State::Key => Err(EncodeError::InvalidKey),
+            State::Value | State::SequenceItem => Ok(()),
+        }
+    }
+
+    fn emit_option_some<F>(&mut self, f: F) -> Result<(), EncodeError>
+                           where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_seq<F>(&mut self, _len: usize, f: F) -> Result<(), EncodeError>
+                   where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        match self.state {
+            State::Value => self.emit_in_state(State::SequenceItem, f),
+            State::Start | State::Fields => Err(EncodeError::TopLevelNotAStructOrMap),
+            State::Key => Err(EncodeError::InvalidKey),
+            State::SequenceItem => Err(EncodeError::Unsupported("nested sequence")),
+        }
+    }
+
+    fn emit_seq_elt<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+                       where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_map<F>(&mut self, _len: usize, f: F) -> Result<(), EncodeError>
+                   where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        self.emit_container("nested map", f)
+    }
+
+    fn emit_map_elt_key<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+                           where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        self.emit_in_state(State::Key, f)
+    }
+
+    fn emit_map_elt_val<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+                           where F: FnOnce(&mut Encoder) -> Result<(), EncodeError> {
+        self.emit_in_state(State::Value, f)
+    }
+}
+
+
+/// A `rustc_serialize::Decoder` that reads (name, value) pairs.
+///
+/// When there are several pairs with the same name for a field that is not a `Vec`,
+/// the first one is used.
+/// A missing boolean field is `false`, like an unchecked HTML checkbox.
+/// `"true"`, `"on"` and `"1"` are `true`, `"false"`, `"off"`, `"0"` and `""` are `false`.
+pub struct Decoder {
+    pairs: Vec<(String, String)>,
+    name: String,
+    values: Vec<String>,
+    index: usize,
+    state: State,
+}
+
+
+impl Decoder {
+    /// Return a new decoder that reads the given pairs.
+    #[inline]
+    pub fn new(pairs: Vec<(String, String)>) -> Decoder {
+        Decoder {
+            pairs: pairs,
+            name: String::new(),
+            values: Vec::new(),
+            index: 0,
+            state: State::Start,
+        }
+    }
+
+    fn read_value(&mut self) -> Result<String, DecodeError> {
+        match self.state {
+            State::Start | State::Fields => Err(DecodeError::TopLevelNotAStructOrMap),
+            State::Key => Ok(self.pairs[self.index].0.clone()),
+            State::Value | State::SequenceItem => match self.values.get(self.index) {
+                Some(value) => Ok(value.clone()),
+                None => Err(DecodeError::MissingField(self.name.clone())),
+            },
+        }
+    }
+
+    fn parse_value<T: FromStr>(&mut self, expected: &'static str) -> Result<T, DecodeError> {
+        let value = try!(self.read_value());
+        match value.parse() {
+            Ok(result) => Ok(result),
+            Err(_) => Err(self.invalid_value(value, expected)),
+        }
+    }
+
+    fn invalid_value(&self, value: String, expected: &'static str) -> DecodeError {
+        DecodeError::InvalidValue { name: self.name.clone(), value: value, expected: expected }
+    }
+
+    fn read_container<T, F>(&mut self, what: &'static str, f: F) -> Result<T, DecodeError>
+                            where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        match self.state {
+            State::Start => {
+                self.state = State::Fields;
+                f(self)
+            },
+            _ => Err(DecodeError::Unsupported(what)),
+        }
+    }
+
+    fn read_in_state<T, F>(&mut self, state: State, f: F) -> Result<T, DecodeError>
+                           where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        let previous = self.state;
+        self.state = state;
+        let result = f(self);
+        self.state = previous;
+        result
+    }
+}
+
+
+impl rustc_serialize::Decoder for Decoder {
+    type Error = DecodeError;
+
+    fn read_nil(&mut self) -> Result<(), DecodeError> { Ok(()) }
+    fn read_usize(&mut self) -> Result<usize, DecodeError> { self.parse_value("unsigned integer") }
+    fn read_u64(&mut self) -> Result<u64, DecodeError> { self.parse_value("unsigned integer") }
+    fn read_u32(&mut self) -> Result<u32, DecodeError> { self.parse_value("unsigned integer") }
+    fn read_u16(&mut self) -> Result<u16, DecodeError> { self.parse_value("unsigned integer") }
+    fn read_u8(&mut self) -> Result<u8, DecodeError> { self.parse_value("unsigned integer") }
+    fn read_isize(&mut self) -> Result<isize, DecodeError> { self.parse_value("integer") }
+    fn read_i64(&mut self) -> Result<i64, DecodeError> { self.parse_value("integer") }
+    fn read_i32(&mut self) -> Result<i32, DecodeError> { self.parse_value("integer") }
+    fn read_i16(&mut self) -> Result<i16, DecodeError> { self.parse_value("integer") }
+    fn read_i8(&mut self) -> Result<i8, DecodeError> { self.parse_value("integer") }
+    fn read_f64(&mut self) -> Result<f64, DecodeError> { self.parse_value("number") }
+    fn read_f32(&mut self) -> Result<f32, DecodeError> { self.parse_value("number") }
+    fn read_str(&mut self) -> Result<String, DecodeError> { self.read_value() }
+
+    fn read_bool(&mut self) -> Result<bool, DecodeError> {
+        if self.state == State::Value && self.values.is_empty() {
+            return Ok(false)
+        }
+        let value = try!(self.read_value());
+        match value.as_slice() {
+            "true" | "on" | "1" => Ok(true),
+            "false" | "off" | "0" | "" => Ok(false),
+            _ => Err(self.invalid_value(value, "boolean")),
+        }
+    }
+
+    fn read_char(&mut self) -> Result<char, DecodeError> {
+        let value = try!(self.read_value());
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(self.invalid_value(value, "single character")),
+        }
+    }
+
+    fn read_enum<T, F>(&mut self, _name: &str, f: F) -> Result<T, DecodeError>
+                       where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        f(self)
+    }
+
+    fn read_enum_variant<T, F>(&mut self, names: &[&str], mut f: F) -> Result<T, DecodeError>
+                               where F: FnMut(&mut Decoder, usize) -> Result<T, DecodeError> {
+        let value = try!(self.read_value());
+        match names.iter().position(|name| *name == value.as_slice()) {
+            Some(index) => f(self, index),
+            None => Err(DecodeError::UnknownVariant { name: self.name.clone(), value: value }),
+        }
+    }
+
+    fn read_enum_variant_arg<T, F>(&mut self, _a_idx: usize, _f: F) -> Result<T, DecodeError>
+                                   where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        Err(DecodeError::Unsupported("enum variant with data"))
+    }
+
+    fn read_enum_struct_variant<T, F>(&mut self, names: &[&str], f: F) -> Result<T, DecodeError>
+                                      where F: FnMut(&mut Decoder, usize)
+                                                     -> Result<T, DecodeError> {
+        self.read_enum_variant(names, f)
+    }
+
+    fn read_enum_struct_variant_field<T, F>(&mut self, _f_name: &str, _f_idx: usize, _f: F)
+                                            -> Result<T, DecodeError>
+                                            where F: FnOnce(&mut Decoder)
+                                                            -> Result<T, DecodeError> {
+        Err(DecodeError::Unsupported("enum variant with data"))
+    }
+
+    fn read_struct<T, F>(&mut self, _s_name: &str, _len: usize, f: F) -> Result<T, DecodeError>
+                         where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        self.read_container("nested struct", f)
+    }
+
+    fn read_struct_field<T, F>(&mut self, f_name: &str, _f_idx: usize, f: F)
+                               -> Result<T, DecodeError>
+                               where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        self.name = f_name.to_string();
+        self.values = self.pairs.iter()
+            .filter(|&&(ref name, _)| name.as_slice() == f_name)
+            .map(|&(_, ref value)| value.clone())
+            .collect();
+        self.index = 0;
+        self.read_in_state(State::Value, f)
+    }
+
+    fn read_tuple<T, F>(&mut self, _len: usize, _f: F) -> Result<T, DecodeError>
+                        where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        Err(DecodeError::Unsupported("tuple"))
+    }
+
+    fn read_tuple_arg<T, F>(&mut self, _a_idx: usize, _f: F) -> Result<T, DecodeError>
+                            where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        Err(DecodeError::Unsupported("tuple"))
+    }
+
+    fn read_tuple_struct<T, F>(&mut self, _s_name: &str, len: usize, f: F)
+                               -> Result<T, DecodeError>
+                               where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        // Newtype structs are decoded from the value they wrap.
+        if len == 1 { f(self) } else { Err(DecodeError::Unsupported("tuple struct")) }
+    }
+
+    fn read_tuple_struct_arg<T, F>(&mut self, _a_idx: usize, f: F) -> Result<T, DecodeError>
+                                   where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        f(self)
+    }
+
+    fn read_option<T, F>(&mut self, mut f: F) -> Result<T, DecodeError>
+                         where F: FnMut(&mut Decoder, bool) -> Result<T, DecodeError> {
+        let is_some = match self.state {
+            State::Value => !self.values.is_empty(),
+            _ => true,
+        };
+        f(self, is_some)
+    }
+
+    fn read_seq<T, F>(&mut self, f: F) -> Result<T, DecodeError>
+                      where F: FnOnce(&mut Decoder, usize) -> Result<T, DecodeError> {
+        match self.state {
+            State::Value => {
+                let len = self.values.len();
+                self.read_in_state(State::SequenceItem, |d| f(d, len))
+            },
+            State::Start => Err(DecodeError::TopLevelNotAStructOrMap),
+            _ => Err(DecodeError::Unsupported("nested sequence")),
+        }
+    }
+
+    fn read_seq_elt<T, F>(&mut self, idx: usize, f: F) -> Result<T, DecodeError>
+                          where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        self.index = idx;
+        f(self)
+    }
+
+    fn read_map<T, F>(&mut self, f: F) -> Result<T, DecodeError>
+                      where F: FnOnce(&mut Decoder, usize) -> Result<T, DecodeError> {
+        let len = self.pairs.len();
+        self.read_container("nested map", |d| f(d, len))
+    }
+
+    fn read_map_elt_key<T, F>(&mut self, idx: usize, f: F) -> Result<T, DecodeError>
+                              where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        self.index = idx;
+        self.name = self.pairs[idx].0.clone();
+        self.read_in_state(State::Key, f)
+    }
+
+    fn read_map_elt_val<T, F>(&mut self, idx: usize, f: F) -> Result<T, DecodeError>
+                              where F: FnOnce(&mut Decoder) -> Result<T, DecodeError> {
+        self.name = self.pairs[idx].0.clone();
+        self.values = vec![self.pairs[idx].1.clone()];
+        self.index = 0;
+        self.read_in_state(State::Value, f)
+    }
+
+    fn error(&mut self, err: &str) -> DecodeError {
+        DecodeError::Custom(err.to_string())
+    }
+}
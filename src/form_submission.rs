@@ -0,0 +1,171 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The URL and body of an [HTML form submission](
+//! https://html.spec.whatwg.org/multipage/forms.html#form-submission-algorithm)
+//! with the default `application/x-www-form-urlencoded` enctype.
+//!
+//! ```
+//! use url::Url;
+//! use url::form_submission::{FormSubmitter, FormMethod};
+//!
+//! let document = Url::parse("http://example.com/search/").unwrap();
+//! let submission = FormSubmitter::new(&document)
+//!     .action("results?old=query#top")
+//!     .method(FormMethod::Get)
+//!     .submit([("q", "rust url")].iter().map(|&pair| pair))
+//!     .unwrap().unwrap();
+//! assert_eq!(submission.url.serialize(),
+//!            "http://example.com/search/results?q=rust+url#top".to_string());
+//! ```
+
+use super::{Url, UrlParser, ParseResult};
+use encoding::EncodingOverride;
+use form_urlencoded;
+
+
+/// The method of a form.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum FormMethod {
+    Get,
+    Post,
+}
+
+
+/// The result of submitting a form.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct FormSubmission {
+    /// The URL to navigate to.
+    pub url: Url,
+
+    /// For `POST` submissions to HTTP(S) URLs, the request body,
+    /// in the `application/x-www-form-urlencoded` format.
+    pub body: Option<String>,
+}
+
+
+/// A method-chaining API to provide the parameters of a form submission.
+pub struct FormSubmitter<'a> {
+    document_url: &'a Url,
+    base_url: &'a Url,
+    action: &'a str,
+    method: FormMethod,
+    encoding_override: EncodingOverride,
+}
+
+
+impl<'a> FormSubmitter<'a> {
+    /// Return a new `FormSubmitter` for a form in the document at `document_url`,
+    /// with an empty action, the `GET` method and UTF-8.
+    #[inline]
+    pub fn new(document_url: &'a Url) -> FormSubmitter<'a> {
+        FormSubmitter {
+            document_url: document_url,
+            base_url: document_url,
+            action: "",
+            method: FormMethod::Get,
+            encoding_override: EncodingOverride::utf8(),
+        }
+    }
+
+    /// Set the base URL the action is resolved against, and return the `FormSubmitter`.
+    /// The default is the document URL.
+    #[inline]
+    pub fn base_url(&mut self, value: &'a Url) -> &mut FormSubmitter<'a> {
+        self.base_url = value;
+        self
+    }
+
+    /// Set the value of the form’s `action` attribute, and return the `FormSubmitter`.
+    /// An empty action, the default, submits to the document URL.
+    #[inline]
+    pub fn action(&mut self, value: &'a str) -> &mut FormSubmitter<'a> {
+        self.action = value;
+        self
+    }
+
+    /// Set the form method, and return the `FormSubmitter`.
+    #[inline]
+    pub fn method(&mut self, value: FormMethod) -> &mut FormSubmitter<'a> {
+        self.method = value;
+        self
+    }
+
+    /// Set the character encoding of the document, used to encode entries,
+    /// and return the `FormSubmitter`. The default is UTF-8.
+    ///
+    /// This method is only available if the `query_encoding` Cargo feature is enabled.
+    #[cfg(feature = "query_encoding")]
+    #[inline]
+    pub fn encoding_override(&mut self, value: ::encoding::EncodingRef)
+                             -> &mut FormSubmitter<'a> {
+        self.encoding_override = EncodingOverride::from_encoding(value);
+        self
+    }
+
+    /// Submit the form with the given (name, value) entries.
+    ///
+    /// Return an error if the action can not be parsed,
+    /// or `Ok(None)` for `javascript:` actions, which are not supported.
+    pub fn submit<'b, I>(&self, entries: I) -> ParseResult<Option<FormSubmission>>
+                         where I: Iterator<Item = (&'b str, &'b str)> {
+        let mut url = if self.action.is_empty() {
+            self.document_url.clone()
+        } else {
+            try!(UrlParser::new().base_url(self.base_url).parse(self.action))
+        };
+        let mut body = None;
+        match (url.scheme.as_slice(), self.method) {
+            ("javascript", _) => return Ok(None),
+
+            // Mutate action URL
+            ("http", FormMethod::Get) | ("https", FormMethod::Get) |
+            ("data", FormMethod::Get) => {
+                url.query = Some(serialize(entries, self.encoding_override));
+            },
+
+            // Submit as entity body
+            ("http", FormMethod::Post) | ("https", FormMethod::Post) => {
+                body = Some(serialize(entries, self.encoding_override));
+            },
+
+            // Mail with headers
+            ("mailto", FormMethod::Get) => {
+                let headers = serialize(entries, self.encoding_override).replace("+", "%20");
+                url.query = Some(headers);
+                url.fragment = None;
+            },
+
+            // Mail as body
+            ("mailto", FormMethod::Post) => {
+                let mail_body = serialize(entries, self.encoding_override).replace("+", "%20");
+                url.query = Some(match url.query.take() {
+                    Some(query) => format!("{}&body={}", query, mail_body),
+                    None => format!("body={}", mail_body),
+                });
+            },
+
+            // Get action URL: navigate to the action, without the entries.
+            _ => {},
+        }
+        Ok(Some(FormSubmission { url: url, body: body }))
+    }
+}
+
+
+#[cfg(feature = "query_encoding")]
+fn serialize<'a, I>(entries: I, encoding_override: EncodingOverride) -> String
+                    where I: Iterator<Item = (&'a str, &'a str)> {
+    form_urlencoded::serialize_with_encoding(entries, Some(encoding_override.to_encoding()))
+}
+
+#[cfg(not(feature = "query_encoding"))]
+fn serialize<'a, I>(entries: I, _encoding_override: EncodingOverride) -> String
+                    where I: Iterator<Item = (&'a str, &'a str)> {
+    form_urlencoded::serialize(entries)
+}
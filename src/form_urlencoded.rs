@@ -14,8 +14,15 @@
 //! and a sequence of (name, value) pairs.
 
 use std::ascii::AsciiExt;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Formatter};
+use std::io;
+use std::iter::repeat;
+use std::str;
 use encoding::EncodingOverride;
-use percent_encoding::{percent_encode_to, percent_decode, FORM_URLENCODED_ENCODE_SET};
+use parser::{ParseResult, ParseError, check_limit};
+use percent_encoding::{percent_decode, FORM_URLENCODED_ENCODE_SET};
 
 
 /// Convert a byte string in the `application/x-www-form-urlencoded` format
@@ -24,7 +31,74 @@ use percent_encoding::{percent_encode_to, percent_decode, FORM_URLENCODED_ENCODE
 /// Use `parse(input.as_bytes())` to parse a `&str` string.
 #[inline]
 pub fn parse(input: &[u8]) -> Vec<(String, String)> {
-    parse_internal(input, EncodingOverride::utf8(), false).unwrap()
+    parse_internal(input, Dialect::whatwg(), EncodingOverride::utf8(), false).unwrap().0
+}
+
+
+/// Return a lazy iterator of the (name, value) pairs
+/// in a byte string in the `application/x-www-form-urlencoded` format.
+///
+/// This gives the same pairs as `parse`, but each name or value borrows from `input`
+/// when it has no `%` or `+` to decode and is valid UTF-8, instead of being allocated.
+#[inline]
+pub fn parse_iter(input: &[u8]) -> ParseIter {
+    ParseIter { input: input }
+}
+
+
+/// An iterator of (name, value) pairs, returned by `parse_iter`.
+pub struct ParseIter<'a> {
+    input: &'a [u8],
+}
+
+
+impl<'a> Iterator for ParseIter<'a> {
+    type Item = (Cow<'a, String, str>, Cow<'a, String, str>);
+
+    fn next(&mut self) -> Option<(Cow<'a, String, str>, Cow<'a, String, str>)> {
+        loop {
+            if self.input.is_empty() {
+                return None
+            }
+            let input = self.input;
+            let piece = match input.position_elem(&b'&') {
+                Some(position) => {
+                    self.input = &input[position + 1..];
+                    &input[..position]
+                },
+                None => {
+                    self.input = &input[input.len()..];
+                    input
+                },
+            };
+            if !piece.is_empty() {
+                let (name, value) = match piece.position_elem(&b'=') {
+                    Some(position) => (&piece[..position], &piece[position + 1..]),
+                    None => (piece, &piece[piece.len()..]),
+                };
+                return Some((decode_component(name), decode_component(value)))
+            }
+        }
+    }
+}
+
+
+fn decode_component(input: &[u8]) -> Cow<String, str> {
+    if !input.iter().any(|&b| b == b'%' || b == b'+') {
+        if let Ok(borrowed) = str::from_utf8(input) {
+            return Cow::Borrowed(borrowed)
+        }
+    }
+    let replaced: Vec<u8> = input.iter().map(|&b| if b == b'+' { b' ' } else { b }).collect();
+    Cow::Owned(String::from_utf8_lossy(percent_decode(replaced.as_slice()).as_slice()).into_owned())
+}
+
+
+/// Convert a byte string in a variant of the `application/x-www-form-urlencoded` format
+/// into a vector of (name, value) pairs.
+#[inline]
+pub fn parse_with_dialect(input: &[u8], dialect: Dialect) -> Vec<(String, String)> {
+    parse_internal(input, dialect, EncodingOverride::utf8(), false).unwrap().0
 }
 
 
@@ -45,44 +119,288 @@ pub fn parse(input: &[u8]) -> Vec<(String, String)> {
 pub fn parse_with_encoding(input: &[u8], encoding_override: Option<::encoding::EncodingRef>,
                            use_charset: bool)
                            -> Option<Vec<(String, String)>> {
-    parse_internal(input, EncodingOverride::from_opt_encoding(encoding_override), use_charset)
+    parse_with_charset(input, encoding_override, use_charset).map(|(pairs, _)| pairs)
 }
 
 
-fn parse_internal(input: &[u8], mut encoding_override: EncodingOverride, mut use_charset: bool)
-                  -> Option<Vec<(String, String)>> {
-    let mut pairs = Vec::new();
-    for piece in input.split(|&b| b == b'&') {
-        if !piece.is_empty() {
-            let (name, value) = match piece.position_elem(&b'=') {
-                Some(position) => (&piece[..position], &piece[position + 1..]),
-                None => (piece, [].as_slice())
-            };
+/// Like `parse_with_encoding`, but also return the character encoding that was used to decode:
+/// either `encoding_override` (or UTF-8 if it is `None`),
+/// or the one named by a `_charset_` pair if `use_charset` is true.
+///
+/// This function is only available if the `query_encoding` Cargo feature is enabled.
+#[cfg(feature = "query_encoding")]
+#[inline]
+pub fn parse_with_charset(input: &[u8], encoding_override: Option<::encoding::EncodingRef>,
+                          use_charset: bool)
+                          -> Option<(Vec<(String, String)>, ::encoding::EncodingRef)> {
+    parse_internal(input, Dialect::whatwg(), EncodingOverride::from_opt_encoding(encoding_override),
+                   use_charset)
+    .map(|(pairs, encoding_override)| (pairs, encoding_override.to_encoding()))
+}
+
+
+/// Which bytes separate (name, value) pairs.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Separator {
+    /// `&`
+    Ampersand,
+    /// `;`, as used by some older Java servlet containers.
+    Semicolon,
+    /// Either `&` or `;` when parsing, as recommended by HTML 4.01.
+    /// `&` is used when serializing.
+    AmpersandOrSemicolon,
+}
+
+
+impl Separator {
+    #[inline]
+    fn matches(self, byte: u8) -> bool {
+        match self {
+            Separator::Ampersand => byte == b'&',
+            Separator::Semicolon => byte == b';',
+            Separator::AmpersandOrSemicolon => byte == b'&' || byte == b';',
+        }
+    }
+
+    #[inline]
+    fn serialization(self) -> &'static str {
+        match self {
+            Separator::Semicolon => ";",
+            Separator::Ampersand | Separator::AmpersandOrSemicolon => "&",
+        }
+    }
+}
+
+
+/// A variant of the `application/x-www-form-urlencoded` format,
+/// for use with `parse_with_dialect` and `serialize_with_dialect`.
+///
+/// Some legacy services differ from the standard on these details.
+/// Matching them byte for byte matters when signing or comparing query strings.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Dialect {
+    /// Which bytes separate pairs.
+    pub separator: Separator,
+
+    /// When parsing, whether `+` is decoded as a space rather than a literal plus sign.
+    pub plus_is_space: bool,
+
+    /// When serializing, whether spaces are encoded as `+` rather than `%20`.
+    pub space_as_plus: bool,
+
+    /// When parsing, whether pieces with no `=`, such as `flag` in `flag&a=1`, are ignored
+    /// rather than taken as a name with an empty value.
+    pub skip_bare_keys: bool,
+
+    /// When serializing, whether pairs with an empty value are written as `name`
+    /// rather than `name=`.
+    pub bare_empty_values: bool,
+
+    /// When parsing, whether empty pieces, such as between `&&`, are kept
+    /// as pairs with an empty name and value rather than ignored.
+    pub keep_empty_pairs: bool,
+}
+
+
+impl Dialect {
+    /// The [URL Standard](http://url.spec.whatwg.org/#application/x-www-form-urlencoded),
+    /// used by `parse` and `serialize`: `&` separators and `+` for spaces.
+    pub fn whatwg() -> Dialect {
+        Dialect {
+            separator: Separator::Ampersand,
+            plus_is_space: true,
+            space_as_plus: true,
+            skip_bare_keys: false,
+            bare_empty_values: false,
+            keep_empty_pairs: false,
+        }
+    }
+
+    /// Like `whatwg`, but `;` is also a separator when parsing,
+    /// as recommended by [HTML 4.01](http://www.w3.org/TR/html401/appendix/notes.html#h-B.2.2).
+    pub fn html4() -> Dialect {
+        Dialect { separator: Separator::AmpersandOrSemicolon, ..Dialect::whatwg() }
+    }
+
+    /// Plain RFC 3986 percent-encoding: `+` is a literal plus sign and spaces are `%20`,
+    /// as expected by many request signing schemes.
+    pub fn percent_encoding_only() -> Dialect {
+        Dialect { plus_is_space: false, space_as_plus: false, ..Dialect::whatwg() }
+    }
+}
+
+
+/// Limits on the size of `application/x-www-form-urlencoded` input,
+/// for use with `parse_with_limits`.
+///
+/// Lengths are in bytes of the input, before percent-decoding.
+/// `None` means no limit.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Limits {
+    /// The maximum number of (name, value) pairs.
+    /// Exceeding it is a `TooManyFormPairs` error.
+    pub max_pairs: Option<usize>,
+
+    /// The maximum length of each name. Exceeding it is a `FormNameTooLong` error.
+    pub max_name_length: Option<usize>,
 
-            #[inline]
-            fn replace_plus(input: &[u8]) -> Vec<u8> {
-                input.iter().map(|&b| if b == b'+' { b' ' } else { b }).collect()
+    /// The maximum length of each value. Exceeding it is a `FormValueTooLong` error.
+    pub max_value_length: Option<usize>,
+}
+
+
+impl Limits {
+    /// No limit at all.
+    pub fn unlimited() -> Limits {
+        Limits {
+            max_pairs: None,
+            max_name_length: None,
+            max_value_length: None,
+        }
+    }
+
+    /// Limits that are generous for legitimate forms and query strings,
+    /// but bound the resources used to parse hostile input:
+    /// 1000 pairs, 1 KiB names, and 64 KiB values.
+    /// This is the default.
+    pub fn recommended() -> Limits {
+        Limits {
+            max_pairs: Some(1000),
+            max_name_length: Some(1024),
+            max_value_length: Some(65536),
+        }
+    }
+}
+
+
+impl Default for Limits {
+    /// Return `Limits::recommended()`.
+    #[inline]
+    fn default() -> Limits {
+        Limits::recommended()
+    }
+}
+
+
+/// Convert a byte string in the `application/x-www-form-urlencoded` format
+/// into a vector of (name, value) pairs, failing if `limits` are exceeded.
+///
+/// The limits are checked before anything is allocated.
+#[inline]
+pub fn parse_with_limits(input: &[u8], limits: Limits) -> ParseResult<Vec<(String, String)>> {
+    parse_with_dialect_and_limits(input, Dialect::whatwg(), limits)
+}
+
+
+/// Convert a byte string in a variant of the `application/x-www-form-urlencoded` format
+/// into a vector of (name, value) pairs, failing if `limits` are exceeded.
+///
+/// The limits are checked before anything is allocated,
+/// on the same pairs as those parsed with `dialect`.
+pub fn parse_with_dialect_and_limits(input: &[u8], dialect: Dialect, limits: Limits)
+                                     -> ParseResult<Vec<(String, String)>> {
+    for (i, (name, value)) in pieces(input, dialect).enumerate() {
+        try!(check_limit(i + 1, limits.max_pairs, ParseError::TooManyFormPairs));
+        try!(check_limit(name.len(), limits.max_name_length, ParseError::FormNameTooLong));
+        try!(check_limit(value.len(), limits.max_value_length, ParseError::FormValueTooLong));
+    }
+    Ok(parse_with_dialect(input, dialect))
+}
+
+
+/// Return an iterator of the raw (name, value) pairs of `input`,
+/// split and filtered as specified by `dialect` but not decoded.
+#[inline]
+fn pieces(input: &[u8], dialect: Dialect) -> Pieces {
+    Pieces { input: Some(input), dialect: dialect }
+}
+
+
+struct Pieces<'a> {
+    /// `None` after the last piece.
+    input: Option<&'a [u8]>,
+    dialect: Dialect,
+}
+
+
+impl<'a> Iterator for Pieces<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<(&'a [u8], &'a [u8])> {
+        loop {
+            let input = match self.input {
+                Some(input) => input,
+                None => return None,
+            };
+            let separator = self.dialect.separator;
+            let piece = match input.iter().position(|&b| separator.matches(b)) {
+                Some(position) => {
+                    self.input = Some(&input[position + 1..]);
+                    &input[..position]
+                },
+                None => {
+                    self.input = None;
+                    input
+                },
+            };
+            if let Some(pair) = split_piece(piece, self.dialect) {
+                return Some(pair)
             }
+        }
+    }
+}
 
-            let name = replace_plus(name);
-            let value = replace_plus(value);
-            if use_charset && name.as_slice() == b"_charset_" {
-                if let Some(encoding) = EncodingOverride::lookup(value.as_slice()) {
-                    encoding_override = encoding;
-                }
-                use_charset = false;
+
+/// Split a piece between separators into its raw name and value,
+/// or return `None` if `dialect` ignores it.
+#[inline]
+fn split_piece(piece: &[u8], dialect: Dialect) -> Option<(&[u8], &[u8])> {
+    if piece.is_empty() && !dialect.keep_empty_pairs {
+        return None
+    }
+    match piece.position_elem(&b'=') {
+        Some(position) => Some((&piece[..position], &piece[position + 1..])),
+        None if dialect.skip_bare_keys && !piece.is_empty() => None,
+        None => Some((piece, &piece[piece.len()..])),
+    }
+}
+
+
+#[inline]
+fn replace_plus(input: &[u8], plus_is_space: bool) -> Vec<u8> {
+    if plus_is_space {
+        input.iter().map(|&b| if b == b'+' { b' ' } else { b }).collect()
+    } else {
+        input.to_vec()
+    }
+}
+
+
+/// Return the pairs and the encoding they were decoded with,
+/// or `None` if the input is not ASCII and the encoding is not UTF-8.
+fn parse_internal(input: &[u8], dialect: Dialect, mut encoding_override: EncodingOverride,
+                  mut use_charset: bool)
+                  -> Option<(Vec<(String, String)>, EncodingOverride)> {
+    let mut pairs = Vec::new();
+    for (name, value) in pieces(input, dialect) {
+        let name = replace_plus(name, dialect.plus_is_space);
+        let value = replace_plus(value, dialect.plus_is_space);
+        if use_charset && name.as_slice() == b"_charset_" {
+            if let Some(encoding) = EncodingOverride::lookup(value.as_slice()) {
+                encoding_override = encoding;
             }
-            pairs.push((name, value));
+            use_charset = false;
         }
+        pairs.push((name, value));
     }
     if !(encoding_override.is_utf8() || input.is_ascii()) {
         return None
     }
 
-    Some(pairs.into_iter().map(|(name, value)| (
+    Some((pairs.into_iter().map(|(name, value)| (
         encoding_override.decode(percent_decode(name.as_slice()).as_slice()),
         encoding_override.decode(percent_decode(value.as_slice()).as_slice())
-    )).collect())
+    )).collect(), encoding_override))
 }
 
 
@@ -98,7 +416,16 @@ pub fn serialize_owned(pairs: &[(String, String)]) -> String {
 /// into a string in the `application/x-www-form-urlencoded` format.
 #[inline]
 pub fn serialize<'a, I>(pairs: I) -> String where I: Iterator<Item = (&'a str, &'a str)> {
-    serialize_internal(pairs, EncodingOverride::utf8())
+    serialize_internal(pairs, Dialect::whatwg(), EncodingOverride::utf8())
+}
+
+
+/// Convert an iterator of (name, value) pairs
+/// into a string in a variant of the `application/x-www-form-urlencoded` format.
+#[inline]
+pub fn serialize_with_dialect<'a, I>(pairs: I, dialect: Dialect) -> String
+                                     where I: Iterator<Item = (&'a str, &'a str)> {
+    serialize_internal(pairs, dialect, EncodingOverride::utf8())
 }
 
 /// Convert an iterator of (name, value) pairs
@@ -110,38 +437,350 @@ pub fn serialize<'a, I>(pairs: I) -> String where I: Iterator<Item = (&'a str, &
 ///
 /// * `encoding_override`: The character encoding each name and values is encoded as
 ///    before percent-encoding. Defaults to UTF-8.
+///    As in HTML form submission, characters that it can not represent
+///    are replaced by numeric character references such as `&#12354;` before percent-encoding,
+///    and UTF-16 encodings are replaced by UTF-8.
 #[cfg(feature = "query_encoding")]
 #[inline]
 pub fn serialize_with_encoding<'a, I>(pairs: I, encoding_override: Option<::encoding::EncodingRef>)
                                       -> String
                                       where I: Iterator<Item = (&'a str, &'a str)> {
-    serialize_internal(pairs, EncodingOverride::from_opt_encoding(encoding_override))
+    serialize_internal(pairs, Dialect::whatwg(),
+                       EncodingOverride::from_opt_encoding(encoding_override))
 }
 
-fn serialize_internal<'a, I>(pairs: I, encoding_override: EncodingOverride) -> String
+fn serialize_internal<'a, I>(pairs: I, dialect: Dialect, encoding_override: EncodingOverride)
+                             -> String
                              where I: Iterator<Item = (&'a str, &'a str)> {
+    let mut output = String::new();
+    let mut first = true;
+    for (name, value) in pairs {
+        serialize_pair(name, value, first, &mut output, dialect, encoding_override);
+        first = false;
+    }
+    output
+}
+
+
+/// Append one (name, value) pair to `output`, preceded by a separator unless it is the first.
+fn serialize_pair(name: &str, value: &str, first: bool, output: &mut String, dialect: Dialect,
+                  encoding_override: EncodingOverride) {
     #[inline]
-    fn byte_serialize(input: &str, output: &mut String,
+    fn byte_serialize(input: &str, output: &mut String, dialect: Dialect,
                       encoding_override: EncodingOverride) {
         for &byte in encoding_override.encode(input).iter() {
-            if byte == b' ' {
+            if byte == b' ' && dialect.space_as_plus {
                 output.push_str("+")
             } else {
-                percent_encode_to(&[byte], FORM_URLENCODED_ENCODE_SET, output)
+                output.push_str(FORM_URLENCODED_ENCODE_SET.encode_byte(byte))
             }
         }
     }
 
-    let mut output = String::new();
-    for (name, value) in pairs {
-        if output.len() > 0 {
-            output.push_str("&");
-        }
-        byte_serialize(name, &mut output, encoding_override);
+    if !first {
+        output.push_str(dialect.separator.serialization());
+    }
+    byte_serialize(name, output, dialect, encoding_override);
+    if !(value.is_empty() && dialect.bare_empty_values) {
         output.push_str("=");
-        byte_serialize(value, &mut output, encoding_override);
+        byte_serialize(value, output, dialect, encoding_override);
+    }
+}
+
+
+/// A target that `Serializer` can write to.
+pub trait Target {
+    /// The error returned when writing fails.
+    type Error;
+
+    /// Write all of `s`.
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error>;
+}
+
+
+/// An `io::Write` target for `Serializer`, such as the body of an HTTP request.
+pub struct IoTarget<W>(pub W);
+
+impl<W: io::Write> Target for IoTarget<W> {
+    type Error = io::Error;
+
+    #[inline]
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.0.write_all(s.as_bytes())
+    }
+}
+
+
+/// A `fmt::Write` target for `Serializer`, such as a `String` or a `Formatter`.
+pub struct FmtTarget<W>(pub W);
+
+impl<W: fmt::Write> Target for FmtTarget<W> {
+    type Error = fmt::Error;
+
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s)
+    }
+}
+
+
+/// A serializer that writes (name, value) pairs one at a time
+/// in the `application/x-www-form-urlencoded` format to an `IoTarget` or a `FmtTarget`.
+///
+/// ```
+/// use url::form_urlencoded::{Serializer, IoTarget, FmtTarget};
+///
+/// let mut serializer = Serializer::new(IoTarget(Vec::new()));
+/// serializer.append_pair("q", "rust url").unwrap();
+/// assert_eq!(serializer.into_inner().0, b"q=rust+url".to_vec());
+///
+/// let mut serializer = Serializer::new(FmtTarget(String::new()));
+/// serializer.append_pair("q", "rust url").unwrap();
+/// assert_eq!(serializer.into_inner().0, "q=rust+url".to_string());
+/// ```
+pub struct Serializer<T> {
+    target: T,
+    dialect: Dialect,
+    first: bool,
+    buffer: String,
+}
+
+
+impl<T: Target> Serializer<T> {
+    /// Return a new serializer that writes to `target` in the standard format.
+    #[inline]
+    pub fn new(target: T) -> Serializer<T> {
+        Serializer::with_dialect(target, Dialect::whatwg())
+    }
+
+    /// Return a new serializer that writes to `target` in a variant of the format.
+    #[inline]
+    pub fn with_dialect(target: T, dialect: Dialect) -> Serializer<T> {
+        Serializer { target: target, dialect: dialect, first: true, buffer: String::new() }
+    }
+
+    /// Serialize and write one (name, value) pair.
+    pub fn append_pair(&mut self, name: &str, value: &str) -> Result<(), T::Error> {
+        self.buffer.truncate(0);
+        serialize_pair(name, value, self.first, &mut self.buffer, self.dialect,
+                       EncodingOverride::utf8());
+        self.first = false;
+        self.target.write_str(self.buffer.as_slice())
+    }
+
+    /// Serialize and write (name, value) pairs.
+    pub fn extend_pairs<'a, I>(&mut self, pairs: I) -> Result<(), T::Error>
+                              where I: Iterator<Item = (&'a str, &'a str)> {
+        for (name, value) in pairs {
+            try!(self.append_pair(name, value));
+        }
+        Ok(())
+    }
+
+    /// Return the underlying target.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.target
+    }
+}
+
+
+/// Errors that can occur when parsing from an `io::Read` source.
+#[derive(Debug)]
+pub enum StreamError {
+    /// Reading failed.
+    Io(io::Error),
+    /// The input exceeds one of the `Limits`.
+    Parse(ParseError),
+    /// The input is not ASCII and the character encoding is not UTF-8,
+    /// for which `parse_with_encoding` returns `None`.
+    InvalidEncoding,
+}
+
+
+impl Error for StreamError {
+    fn description(&self) -> &str {
+        match *self {
+            StreamError::Io(ref error) => error.description(),
+            StreamError::Parse(ref error) => error.description(),
+            StreamError::InvalidEncoding => "non-ASCII input with an encoding other than UTF-8",
+        }
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            StreamError::Io(ref error) => error.fmt(fmt),
+            StreamError::Parse(ref error) => error.fmt(fmt),
+            StreamError::InvalidEncoding => self.description().fmt(fmt),
+        }
+    }
+}
+
+
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+
+/// Return an iterator that reads `application/x-www-form-urlencoded` input from `reader`
+/// and parses it incrementally into (name, value) pairs, such as a large POST body.
+///
+/// Only one pair is buffered at a time, and `limits` bound its size and the number of pairs,
+/// so that memory use stays bounded.
+/// Percent-encoded sequences and `+` are decoded the same way as `parse`,
+/// even when they are split across reads.
+/// The iteration ends after the first error.
+#[inline]
+pub fn parse_stream<R: io::Read>(reader: R, limits: Limits) -> StreamParser<R> {
+    stream_parser(reader, Dialect::whatwg(), EncodingOverride::utf8(), limits)
+}
+
+
+/// Like `parse_stream`, but for a variant of the `application/x-www-form-urlencoded` format.
+/// This gives the same pairs as `parse_with_dialect`.
+#[inline]
+pub fn parse_stream_with_dialect<R: io::Read>(reader: R, dialect: Dialect, limits: Limits)
+                                              -> StreamParser<R> {
+    stream_parser(reader, dialect, EncodingOverride::utf8(), limits)
+}
+
+
+/// Like `parse_stream_with_dialect`, but decode names and values
+/// with the given character encoding after percent-decoding. Defaults to UTF-8.
+///
+/// Since pairs are returned as they are read,
+/// a `_charset_` pair can not change how earlier pairs were decoded
+/// and is not taken into account.
+/// If the input is not ASCII and the encoding is not UTF-8,
+/// iteration ends with a `StreamError::InvalidEncoding` error at the first such pair.
+///
+/// This function is only available if the `query_encoding` Cargo feature is enabled.
+#[cfg(feature = "query_encoding")]
+#[inline]
+pub fn parse_stream_with_encoding<R: io::Read>(reader: R, dialect: Dialect,
+                                               encoding_override: Option<::encoding::EncodingRef>,
+                                               limits: Limits)
+                                               -> StreamParser<R> {
+    stream_parser(reader, dialect, EncodingOverride::from_opt_encoding(encoding_override), limits)
+}
+
+
+fn stream_parser<R: io::Read>(reader: R, dialect: Dialect, encoding_override: EncodingOverride,
+                              limits: Limits) -> StreamParser<R> {
+    StreamParser {
+        reader: reader,
+        dialect: dialect,
+        encoding_override: encoding_override,
+        limits: limits,
+        buffer: repeat(0).take(READ_BUFFER_SIZE).collect(),
+        position: 0,
+        end: 0,
+        piece: Vec::new(),
+        pairs: 0,
+        done: false,
+    }
+}
+
+
+/// An iterator of (name, value) pairs read from an `io::Read` source,
+/// returned by `parse_stream` and the related functions.
+pub struct StreamParser<R> {
+    reader: R,
+    dialect: Dialect,
+    encoding_override: EncodingOverride,
+    limits: Limits,
+    buffer: Vec<u8>,
+    position: usize,
+    end: usize,
+    /// The bytes of the current piece between separators, read so far.
+    piece: Vec<u8>,
+    pairs: usize,
+    /// Whether the end of the input was reached.
+    done: bool,
+}
+
+
+impl<R: io::Read> StreamParser<R> {
+    fn check_piece_limits(&self) -> Result<(), ParseError> {
+        let (name_length, value_length) = match self.piece.position_elem(&b'=') {
+            Some(position) => (position, self.piece.len() - position - 1),
+            None => (self.piece.len(), 0),
+        };
+        try!(check_limit(name_length, self.limits.max_name_length,
+                         ParseError::FormNameTooLong));
+        check_limit(value_length, self.limits.max_value_length, ParseError::FormValueTooLong)
+    }
+
+    /// Read into `piece` up to the next separator, or to the end of the input.
+    ///
+    /// As with `split`, the input always has one more piece than separators,
+    /// so the last piece is read when the end of the input is reached, even if it is empty.
+    fn read_piece(&mut self) -> Result<(), StreamError> {
+        self.piece.truncate(0);
+        loop {
+            if self.position == self.end {
+                match self.reader.read(self.buffer.as_mut_slice()) {
+                    Ok(0) => {
+                        self.done = true;
+                        return Ok(())
+                    },
+                    Ok(length) => {
+                        self.position = 0;
+                        self.end = length;
+                    },
+                    Err(ref error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(error) => return Err(StreamError::Io(error)),
+                }
+            }
+            let separator = self.dialect.separator;
+            let available = &self.buffer[self.position..self.end];
+            let found = available.iter().position(|&b| separator.matches(b));
+            let length = found.unwrap_or(available.len());
+            self.piece.push_all(&available[..length]);
+            self.position += found.map_or(length, |position| position + 1);
+            try!(self.check_piece_limits().map_err(StreamError::Parse));
+            if found.is_some() {
+                return Ok(())
+            }
+        }
+    }
+
+    fn next_pair(&mut self) -> Result<Option<(String, String)>, StreamError> {
+        loop {
+            if self.done {
+                return Ok(None)
+            }
+            try!(self.read_piece());
+            let (name, value) = match split_piece(self.piece.as_slice(), self.dialect) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            self.pairs += 1;
+            try!(check_limit(self.pairs, self.limits.max_pairs, ParseError::TooManyFormPairs)
+                 .map_err(StreamError::Parse));
+            if !(self.encoding_override.is_utf8() || self.piece.is_ascii()) {
+                return Err(StreamError::InvalidEncoding)
+            }
+            let decode = |input: &[u8]| self.encoding_override.decode(percent_decode(
+                replace_plus(input, self.dialect.plus_is_space).as_slice()).as_slice());
+            return Ok(Some((decode(name), decode(value))))
+        }
+    }
+}
+
+
+impl<R: io::Read> Iterator for StreamParser<R> {
+    type Item = Result<(String, String), StreamError>;
+
+    fn next(&mut self) -> Option<Result<(String, String), StreamError>> {
+        match self.next_pair() {
+            Ok(pair) => pair.map(Ok),
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            },
+        }
     }
-    output
 }
 
 
@@ -156,3 +795,135 @@ fn test_form_urlencoded() {
     assert_eq!(encoded.as_slice(), "foo=%C3%A9%26&bar=&foo=%23");
     assert_eq!(parse(encoded.as_bytes()), pairs.as_slice().to_vec());
 }
+
+
+#[test]
+fn test_form_urlencoded_limits() {
+    let limits = Limits { max_pairs: Some(2), max_name_length: Some(3), max_value_length: Some(4) };
+    assert_eq!(parse_with_limits(b"a=1&&bcd=efgh", limits),
+               Ok(vec![("a".to_string(), "1".to_string()), ("bcd".to_string(), "efgh".to_string())]));
+    assert_eq!(parse_with_limits(b"a&b&c", limits), Err(ParseError::TooManyFormPairs));
+    assert_eq!(parse_with_limits(b"abcd=1", limits), Err(ParseError::FormNameTooLong));
+    assert_eq!(parse_with_limits(b"a=12345", limits), Err(ParseError::FormValueTooLong));
+    assert!(parse_with_limits(&[b'&'; 10000], Limits::recommended()).is_ok());
+
+    // Pairs are counted and measured as split by the dialect.
+    assert_eq!(parse_with_limits(b"a=1;b=2;c=3", limits), Err(ParseError::FormValueTooLong));
+    assert_eq!(parse_with_dialect_and_limits(b"a=1;b=2;c=3", Dialect::html4(), limits),
+               Err(ParseError::TooManyFormPairs));
+    let dialect = Dialect { skip_bare_keys: true, ..Dialect::html4() };
+    assert_eq!(parse_with_dialect_and_limits(b"a=1;flag;b=2", dialect, limits),
+               Ok(vec![("a".to_string(), "1".to_string()), ("b".to_string(), "2".to_string())]));
+}
+
+
+#[test]
+fn test_form_urlencoded_dialects() {
+    let input = b"a=1+2;b=%2B&&flag&c=";
+    let pair = |n: &str, v: &str| (n.to_string(), v.to_string());
+    assert_eq!(parse(input), vec![pair("a", "1 2;b=+"), pair("flag", ""), pair("c", "")]);
+    assert_eq!(parse_with_dialect(input, Dialect::html4()),
+               vec![pair("a", "1 2"), pair("b", "+"), pair("flag", ""), pair("c", "")]);
+
+    let dialect = Dialect {
+        separator: Separator::Semicolon,
+        skip_bare_keys: true,
+        keep_empty_pairs: true,
+        ..Dialect::percent_encoding_only()
+    };
+    assert_eq!(parse_with_dialect(b"a=1+2;;flag;c=%20", dialect),
+               vec![pair("a", "1+2"), pair("", ""), pair("c", " ")]);
+
+    let pairs = [("a", "1 2"), ("flag", ""), ("b", "+")];
+    assert_eq!(serialize_with_dialect(pairs.iter().map(|&p| p), Dialect::whatwg()).as_slice(),
+               "a=1+2&flag=&b=%2B");
+    let dialect = Dialect { bare_empty_values: true, ..Dialect::percent_encoding_only() };
+    assert_eq!(serialize_with_dialect(pairs.iter().map(|&p| p), dialect).as_slice(),
+               "a=1%202&flag&b=%2B");
+    let dialect = Dialect { separator: Separator::Semicolon, ..Dialect::whatwg() };
+    assert_eq!(serialize_with_dialect(pairs.iter().map(|&p| p), dialect).as_slice(),
+               "a=1+2;flag=;b=%2B");
+}
+
+
+#[test]
+fn test_form_urlencoded_parse_iter() {
+    fn is_borrowed(value: &Cow<String, str>) -> bool {
+        match *value { Cow::Borrowed(_) => true, Cow::Owned(_) => false }
+    }
+
+    let input = b"a=1&&b=x+y&c%20d=%C3%A9&flag&=&e=\xFF";
+    let pairs: Vec<_> = parse_iter(input).collect();
+    assert_eq!(pairs.iter().map(|&(ref n, ref v)| (n.to_string(), v.to_string()))
+                    .collect::<Vec<_>>(),
+               parse(input));
+    assert_eq!(pairs.iter().map(|&(ref n, ref v)| (is_borrowed(n), is_borrowed(v)))
+                    .collect::<Vec<_>>(),
+               vec![(true, true), (true, false), (false, false), (true, true), (true, true),
+                    (true, false)]);
+}
+
+
+#[test]
+fn test_form_urlencoded_streaming() {
+    /// Return one byte per read, so that every sequence is split across reads.
+    struct OneByteReader<'a>(&'a [u8]);
+
+    impl<'a> io::Read for OneByteReader<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buffer.is_empty() {
+                return Ok(0)
+            }
+            buffer[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    let input = b"a=%C3%A9+x&&b&c=%2B+%25";
+    let pairs = parse_stream(OneByteReader(input), Limits::unlimited())
+        .map(|pair| pair.unwrap()).collect::<Vec<_>>();
+    assert_eq!(pairs, parse(input));
+    let pairs = parse_stream(input.as_slice(), Limits::recommended())
+        .map(|pair| pair.unwrap()).collect::<Vec<_>>();
+    assert_eq!(pairs, parse(input));
+
+    let limits = Limits { max_pairs: Some(2), max_name_length: Some(3), max_value_length: Some(4) };
+    let mut stream = parse_stream(OneByteReader(b"a=1&bb=2&c=3"), limits);
+    assert!(stream.next().unwrap().is_ok());
+    assert!(stream.next().unwrap().is_ok());
+    match stream.next() {
+        Some(Err(StreamError::Parse(ParseError::TooManyFormPairs))) => (),
+        _ => panic!("expected TooManyFormPairs"),
+    }
+    assert!(stream.next().is_none());
+    let mut stream = parse_stream(OneByteReader(b"a=12345678"), limits);
+    match stream.next() {
+        Some(Err(StreamError::Parse(ParseError::FormValueTooLong))) => (),
+        _ => panic!("expected FormValueTooLong"),
+    }
+
+    // Dialects give the same pairs as `parse_with_dialect`.
+    let dialects = [
+        Dialect::html4(),
+        Dialect { skip_bare_keys: true, ..Dialect::percent_encoding_only() },
+        Dialect { separator: Separator::Semicolon, keep_empty_pairs: true, ..Dialect::whatwg() },
+    ];
+    for &dialect in dialects.iter() {
+        for &input in [&b"a=1+2;b=%2B&&flag;;c=&"[..], &b""[..], &b";"[..]].iter() {
+            let stream = parse_stream_with_dialect(OneByteReader(input), dialect,
+                                                   Limits::unlimited());
+            let pairs = stream.map(|pair| pair.unwrap()).collect::<Vec<_>>();
+            assert_eq!(pairs, parse_with_dialect(input, dialect));
+        }
+    }
+
+    let pairs = [("a", "é x"), ("b", ""), ("c", "+%")];
+    let mut serializer = Serializer::new(IoTarget(Vec::new()));
+    serializer.extend_pairs(pairs.iter().map(|&p| p)).unwrap();
+    let mut fmt_serializer = Serializer::new(FmtTarget(String::new()));
+    fmt_serializer.extend_pairs(pairs.iter().map(|&p| p)).unwrap();
+    let expected = serialize(pairs.iter().map(|&p| p));
+    assert_eq!(serializer.into_inner().0, expected.as_bytes().to_vec());
+    assert_eq!(fmt_serializer.into_inner().0, expected);
+}
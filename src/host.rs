@@ -41,6 +41,8 @@ impl Host {
     ///
     /// Returns `Err` for an empty host, an invalid IPv6 address,
     /// or a or invalid non-ASCII domain.
+    /// Percent-encoded bytes in a domain that are not valid UTF-8
+    /// are an `InvalidUtf8InHost` error.
     ///
     /// FIXME: Add IDNA support for non-ASCII domains.
     pub fn parse(input: &str) -> ParseResult<Host> {
@@ -53,8 +55,10 @@ impl Host {
                 Err(ParseError::InvalidIpv6Address)
             }
         } else {
-            let decoded = percent_decode(input.as_bytes());
-            let domain = String::from_utf8_lossy(decoded.as_slice());
+            let domain = match String::from_utf8(percent_decode(input.as_bytes())) {
+                Ok(domain) => domain,
+                Err(_) => return Err(ParseError::InvalidUtf8InHost),
+            };
             // TODO: Remove this check and use IDNA "domain to ASCII"
             if !domain.as_slice().is_ascii() {
                 Err(ParseError::NonAsciiDomainsNotSupportedYet)
@@ -63,7 +67,7 @@ impl Host {
             ].as_slice()).is_some() {
                 Err(ParseError::InvalidDomainCharacter)
             } else {
-                Ok(Host::Domain(domain.to_string().into_ascii_lowercase()))
+                Ok(Host::Domain(domain.into_ascii_lowercase()))
             }
         }
     }
@@ -273,3 +277,71 @@ fn longest_zero_sequence(pieces: &[u16; 8]) -> (isize, isize) {
     finish_sequence!(8);
     (longest, longest + longest_length)
 }
+
+
+/// Parse an IPv4 address the way browsers do:
+/// with one to four `.`-separated numbers, each in decimal, octal (with a leading `0`)
+/// or hexadecimal (with a leading `0x`), the last number filling the remaining bytes.
+/// For example, `0xC3.127.11` and `3279880203` are both `195.127.0.11`.
+///
+/// Return `None` if `input` is not such an address.
+pub fn parse_ipv4(input: &[u8]) -> Option<[u8; 4]> {
+    let parts: Vec<&[u8]> = input.split(|&b| b == b'.').collect();
+    if parts.len() > 4 {
+        return None
+    }
+    let mut numbers = Vec::with_capacity(parts.len());
+    for part in parts.iter() {
+        let (digits, radix) = if part.len() >= 2 && part[0] == b'0' &&
+                                 (part[1] == b'x' || part[1] == b'X') {
+            (&part[2..], 16)
+        } else if part.len() >= 2 && part[0] == b'0' {
+            (&part[1..], 8)
+        } else if part.is_empty() {
+            return None
+        } else {
+            (&part[..], 10)
+        };
+        let mut number: u64 = 0;
+        for &byte in digits.iter() {
+            let digit = match from_hex(byte) {
+                Some(digit) if (digit as u64) < radix => digit as u64,
+                _ => return None,
+            };
+            number = number * radix + digit;
+            if number > 0xFFFFFFFF {
+                return None
+            }
+        }
+        numbers.push(number);
+    }
+    let last = numbers.pop().unwrap();
+    if numbers.iter().any(|&number| number > 0xFF) ||
+            last >= 1 << (8 * (4 - numbers.len())) {
+        return None
+    }
+    let mut address = last;
+    for (i, &number) in numbers.iter().enumerate() {
+        address += number << (8 * (3 - i));
+    }
+    Some([(address >> 24) as u8, (address >> 16) as u8, (address >> 8) as u8, address as u8])
+}
+
+
+#[test]
+fn test_parse_ipv4() {
+    assert_eq!(parse_ipv4(b"195.127.0.11"), Some([195, 127, 0, 11]));
+    assert_eq!(parse_ipv4(b"3279880203"), Some([195, 127, 0, 11]));
+    assert_eq!(parse_ipv4(b"0xC3.127.11"), Some([195, 127, 0, 11]));
+    assert_eq!(parse_ipv4(b"0303.0177.0x0.013"), Some([195, 127, 0, 11]));
+    assert_eq!(parse_ipv4(b"0x"), Some([0, 0, 0, 0]));
+    assert_eq!(parse_ipv4(b"1.2.65535"), Some([1, 2, 255, 255]));
+    assert_eq!(parse_ipv4(b"1.2.65536"), None);
+    assert_eq!(parse_ipv4(b"256.1"), None);
+    assert_eq!(parse_ipv4(b"4294967296"), None);
+    assert_eq!(parse_ipv4(b"1.2.3.4.5"), None);
+    assert_eq!(parse_ipv4(b"1..2"), None);
+    assert_eq!(parse_ipv4(b"08"), None);
+    assert_eq!(parse_ipv4(b"example.com"), None);
+    assert_eq!(parse_ipv4(b""), None);
+}
@@ -0,0 +1,160 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers for URLs in HTTP messages.
+//!
+//! On the client side, see the `request_target`, `proxy_request_target`,
+//! `connect_request_target`, `host_header` and `http2_pseudo_headers` methods of `Url`.
+//!
+//! On the server side, parse the [request-target](
+//! http://tools.ietf.org/html/rfc7230#section-5.3) of a request
+//! and reconstruct its [effective request URI](
+//! http://tools.ietf.org/html/rfc7230#section-5.5).
+//!
+//! ```
+//! use url::http::{parse_request_target, effective_request_uri};
+//!
+//! let target = parse_request_target("/search?q=rust").unwrap();
+//! let url = effective_request_uri(&target, Some("example.com:8080"), "http").unwrap();
+//! assert_eq!(url.serialize(), "http://example.com:8080/search?q=rust".to_string());
+//! ```
+
+use super::{Url, UrlParser, Host, SchemeType};
+use format::PathFormatter;
+use parser::{self, ParseResult, ParseError, Context};
+
+
+/// The request-target of an HTTP request, in one of its four forms.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum RequestTarget {
+    /// `origin-form`: an absolute path and optional query string, as in `GET /where?q=now`.
+    ///
+    /// This is the form used in most requests.
+    /// The path and query string are percent-encoded, as in `Url`.
+    Origin {
+        path: Vec<String>,
+        query: Option<String>,
+    },
+
+    /// `absolute-form`: an absolute URL, as in `GET http://www.example.org/pub/`.
+    ///
+    /// This is the form used in requests to proxies.
+    Absolute(Url),
+
+    /// `authority-form`: a host and port number, as in `CONNECT www.example.com:80`.
+    ///
+    /// This is the form used in `CONNECT` requests.
+    Authority(Host, u16),
+
+    /// `asterisk-form`: `*`, as in `OPTIONS *`.
+    ///
+    /// This is the form used in server-wide `OPTIONS` requests.
+    Asterisk,
+}
+
+
+/// The pseudo-header fields of an HTTP/2 request
+/// ([HTTP/2 §8.1.2.3](http://http2.github.io/http2-spec/#HttpRequest)),
+/// as returned by `Url::http2_pseudo_headers`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PseudoHeaders {
+    /// The `:scheme` pseudo-header field.
+    pub scheme: String,
+
+    /// The `:authority` pseudo-header field: the host and non-default port number.
+    pub authority: String,
+
+    /// The `:path` pseudo-header field: the path and query string.
+    pub path: String,
+}
+
+
+/// Parse the request-target from the start line of an HTTP request.
+///
+/// Any fragment identifier in the origin form is parsed and ignored,
+/// like many servers do.
+pub fn parse_request_target(input: &str) -> ParseResult<RequestTarget> {
+    if input == "*" {
+        return Ok(RequestTarget::Asterisk)
+    }
+    if input.starts_with("/") {
+        let (path, query, _fragment) = try!(::parse_path(input));
+        return Ok(RequestTarget::Origin { path: path, query: query })
+    }
+    match parser::parse_scheme(input, Context::UrlParser) {
+        // `www.example.com:80` would otherwise be parsed as a scheme and scheme data.
+        Some((_, remaining)) if !remaining.bytes().all(|b| matches!(b, b'0'...b'9')) => {
+            return Url::parse(input).map(RequestTarget::Absolute)
+        },
+        _ => (),
+    }
+    let (host, port) = try!(parse_authority(input));
+    match port {
+        Some(port) => Ok(RequestTarget::Authority(host, port)),
+        // CONNECT requires a port number
+        None => Err(ParseError::InvalidRequestTarget),
+    }
+}
+
+
+/// Parse and validate the value of a `Host` header: a host and optional port number.
+pub fn parse_host_header(input: &str) -> ParseResult<(Host, Option<u16>)> {
+    parse_authority(input).map_err(|error| match error {
+        ParseError::InvalidRequestTarget => ParseError::InvalidHostHeader,
+        error => error,
+    })
+}
+
+
+fn parse_authority(input: &str) -> ParseResult<(Host, Option<u16>)> {
+    let parser = UrlParser::new();
+    // File-like schemes have no default port, so that the port is always kept.
+    let (host, port, _, remaining) = try!(parser::parse_host(
+        input, SchemeType::FileLike, &parser));
+    if !remaining.is_empty() {
+        return Err(ParseError::InvalidRequestTarget)
+    }
+    Ok((host, port))
+}
+
+
+/// Reconstruct the effective request URI of an HTTP request, per RFC 7230 §5.5.
+///
+/// Arguments:
+///
+/// * `target`: The parsed request-target.
+/// * `host_header`: The value of the `Host` header, if any.
+///   It is required unless `target` is in absolute form.
+/// * `scheme`: The scheme of the connection the request was received on,
+///   typically `"http"` or `"https"`.
+pub fn effective_request_uri(target: &RequestTarget, host_header: Option<&str>, scheme: &str)
+                             -> ParseResult<Url> {
+    let authority = match *target {
+        RequestTarget::Absolute(ref url) => return Ok(url.clone()),
+        RequestTarget::Authority(ref host, port) => format!("{}:{}", host, port),
+        _ => match host_header {
+            Some(host_header) if !host_header.is_empty() => {
+                let (host, port) = try!(parse_host_header(host_header));
+                match port {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.serialize(),
+                }
+            },
+            _ => return Err(ParseError::EmptyHost),
+        },
+    };
+    let mut input = format!("{}://{}", scheme, authority);
+    if let RequestTarget::Origin { ref path, ref query } = *target {
+        input.push_str(PathFormatter { path: path.as_slice() }.to_string().as_slice());
+        if let Some(ref query) = *query {
+            input.push_str("?");
+            input.push_str(query.as_slice());
+        }
+    }
+    Url::parse(input.as_slice())
+}
@@ -119,7 +119,7 @@ assert!(css_url.serialize() == "http://servo.github.io/rust-url/main.css".to_str
 */
 
 
-#![feature(core, std_misc, collections, path, hash)]
+#![feature(core, std_misc, collections, path, hash, unsafe_destructor, io)]
 
 extern crate "rustc-serialize" as rustc_serialize;
 
@@ -128,10 +128,14 @@ extern crate matches;
 
 use std::fmt::{self, Formatter};
 use std::hash;
+use std::ops::Range;
 use std::old_path;
 
 pub use host::{Host, Ipv6Address};
 pub use parser::{ErrorHandler, ParseResult, ParseError};
+pub use query_pairs::QueryPairsMut;
+pub use path_segments::{PathSegments, LossyDecodedPathSegments, PathSegmentsMut};
+pub use position::{Position, SerializedUrl};
 
 #[deprecated = "Moved to the `percent_encoding` module"]
 pub use percent_encoding::{
@@ -147,11 +151,26 @@ use encoding::EncodingOverride;
 mod encoding;
 mod host;
 mod parser;
+mod path_segments;
+mod position;
+mod query_pairs;
+mod rfc3986;
+mod scan;
 mod urlutils;
 pub mod percent_encoding;
 pub mod form_urlencoded;
+pub mod form_serialize;
+pub mod form_nested;
+pub mod form_submission;
+pub mod matrix;
+pub mod normalize;
+pub mod clean;
+pub mod surt;
+pub mod safe_browsing;
 pub mod punycode;
 pub mod format;
+pub mod fixup;
+pub mod http;
 
 #[cfg(test)]
 mod tests;
@@ -255,6 +274,8 @@ pub struct UrlParser<'a> {
     query_encoding_override: EncodingOverride,
     error_handler: ErrorHandler,
     scheme_type_mapper: fn(scheme: &str) -> SchemeType,
+    strict_rfc3986: bool,
+    limits: ParseLimits,
 }
 
 
@@ -269,6 +290,8 @@ impl<'a> UrlParser<'a> {
             query_encoding_override: EncodingOverride::utf8(),
             error_handler: silent_handler,
             scheme_type_mapper: whatwg_scheme_type_mapper,
+            strict_rfc3986: false,
+            limits: ParseLimits::recommended(),
         }
     }
 
@@ -284,6 +307,9 @@ impl<'a> UrlParser<'a> {
     /// and return the `UrlParser`.
     ///
     /// This legacy quirk is only relevant to HTML.
+    /// Characters that the encoding can not represent are replaced
+    /// by numeric character references such as `&#12354;` before percent-encoding,
+    /// and UTF-16 encodings are replaced by UTF-8.
     ///
     /// This method is only available if the `query_encoding` Cargo feature is enabled.
     #[cfg(feature = "query_encoding")]
@@ -343,12 +369,89 @@ impl<'a> UrlParser<'a> {
         self
     }
 
+    /// Enable or disable strict [RFC 3986](http://tools.ietf.org/html/rfc3986) parsing,
+    /// and return the `UrlParser`.
+    ///
+    /// In strict mode, the input must match the `URI` rule of RFC 3986
+    /// (or `URI-reference` if a base URL was given) exactly.
+    /// Leading and trailing whitespace, backslashes, tabs and newlines,
+    /// unencoded spaces or non-ASCII characters, and relative-scheme URLs
+    /// without two slashes (such as `http:example.com`) are rejected
+    /// rather than fixed up.
+    /// Every non-fatal parse error also becomes fatal,
+    /// and the error handler is not called.
+    ///
+    /// The only rewriting left is the normalization allowed by RFC 3986:
+    /// case normalization of the scheme and host, removal of dot-segments,
+    /// and removal of the default port.
+    ///
+    /// The default is the lenient [URL Standard](http://url.spec.whatwg.org/) parser.
+    #[inline]
+    pub fn strict_rfc3986<'b>(&'b mut self, value: bool) -> &'b mut UrlParser<'a> {
+        self.strict_rfc3986 = value;
+        self
+    }
+
+    /// Set limits on the size of the input, and return the `UrlParser`.
+    ///
+    /// Parsing fails as soon as a limit is exceeded,
+    /// before the corresponding component is fully allocated.
+    /// The default is `ParseLimits::recommended()`, so that untrusted input is bounded.
+    /// Use `ParseLimits::unlimited()` for trusted input that can be longer,
+    /// such as large `data:` URLs.
+    #[inline]
+    pub fn limits<'b>(&'b mut self, value: ParseLimits) -> &'b mut UrlParser<'a> {
+        self.limits = value;
+        self
+    }
+
     /// Parse `input` as an URL, with all the parameters previously set in the `UrlParser`.
     #[inline]
     pub fn parse(&self, input: &str) -> ParseResult<Url> {
         parser::parse_url(input, self)
     }
 
+    /// Parse `input` as an URL, with all the parameters previously set in the `UrlParser`,
+    /// and store the result in `url`.
+    ///
+    /// The new value is written in the strings and vectors of the previous value of `url`,
+    /// so that parsing many URLs in a row into the same `Url` mostly avoids allocating.
+    /// The previous value is therefore lost even if parsing fails,
+    /// in which case `url` is set to `about:blank`.
+    /// Keeping the previous value would take a copy of it before every parse.
+    ///
+    /// ```
+    /// use url::{Url, UrlParser};
+    ///
+    /// let parser = UrlParser::new();
+    /// let mut url = Url::parse("http://example.com/").unwrap();
+    /// for line in ["https://example.net/a?b", "mailto:someone@example.com"].iter() {
+    ///     parser.parse_into(line, &mut url).unwrap();
+    ///     assert_eq!(url.serialize(), line.to_string());
+    /// }
+    /// ```
+    #[inline]
+    pub fn parse_into(&self, input: &str, url: &mut Url) -> ParseResult<()> {
+        parser::parse_url_into(input, self, url)
+    }
+
+    /// Parse `input` as an URL from raw bytes,
+    /// with all the parameters previously set in the `UrlParser`.
+    ///
+    /// This is for input that is not known to be UTF-8,
+    /// such as the start line of an HTTP request or attribute values in an HTML byte stream.
+    /// Bytes that are not part of well-formed UTF-8 sequences are percent-encoded
+    /// in the username, password, path, query string and fragment identifier.
+    /// Such bytes in the scheme or host are an `InvalidUtf8InScheme`
+    /// or `InvalidUtf8InHost` error, respectively.
+    ///
+    /// The `max_length` limit applies to the length of `input`.
+    /// The other limits apply after these bytes are percent-encoded.
+    #[inline]
+    pub fn parse_bytes(&self, input: &[u8]) -> ParseResult<Url> {
+        parser::parse_url_bytes(input, self)
+    }
+
     /// Parse `input` as a “standalone” URL path,
     /// with an optional query string and fragment identifier.
     ///
@@ -391,10 +494,77 @@ pub fn parse_path(input: &str)
 }
 
 
+/// Limits on the size of the input to URL parsing, for use with `UrlParser::limits`.
+///
+/// Lengths are in bytes of the input, before percent-encoding.
+/// `None` means no limit.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct ParseLimits {
+    /// The maximum length of the entire input. Exceeding it is a `UrlTooLong` error.
+    pub max_length: Option<usize>,
+
+    /// The maximum length of the host. Exceeding it is a `HostTooLong` error.
+    pub max_host_length: Option<usize>,
+
+    /// The maximum number of path components, including those taken from a base URL.
+    /// Exceeding it is a `TooManyPathSegments` error.
+    pub max_path_segments: Option<usize>,
+
+    /// The maximum length of each path component.
+    /// Exceeding it is a `PathSegmentTooLong` error.
+    pub max_path_segment_length: Option<usize>,
+
+    /// The maximum length of the query string. Exceeding it is a `QueryTooLong` error.
+    pub max_query_length: Option<usize>,
+}
+
+
+impl ParseLimits {
+    /// No limit at all.
+    pub fn unlimited() -> ParseLimits {
+        ParseLimits {
+            max_length: None,
+            max_host_length: None,
+            max_path_segments: None,
+            max_path_segment_length: None,
+            max_query_length: None,
+        }
+    }
+
+    /// Limits that are generous for legitimate URLs,
+    /// but bound the resources used to parse hostile input:
+    /// 8 KiB in total (a common limit for HTTP request lines),
+    /// 255 bytes for the host (the DNS limit),
+    /// 128 path components of up to 2 KiB each, and an 8 KiB query string.
+    /// This is the default.
+    pub fn recommended() -> ParseLimits {
+        ParseLimits {
+            max_length: Some(8192),
+            max_host_length: Some(255),
+            max_path_segments: Some(128),
+            max_path_segment_length: Some(2048),
+            max_query_length: Some(8192),
+        }
+    }
+}
+
+
+impl Default for ParseLimits {
+    /// Return `ParseLimits::recommended()`.
+    #[inline]
+    fn default() -> ParseLimits {
+        ParseLimits::recommended()
+    }
+}
+
+
 /// Private convenience methods for use in parser.rs
 impl<'a> UrlParser<'a> {
     #[inline]
     fn parse_error(&self, error: ParseError) -> ParseResult<()> {
+        if self.strict_rfc3986 {
+            return Err(error)
+        }
         (self.error_handler)(error)
     }
 
@@ -462,7 +632,8 @@ pub fn whatwg_scheme_type_mapper(scheme: &str) -> SchemeType {
 impl Url {
     /// Parse an URL with the default `UrlParser` parameters.
     ///
-    /// In particular, relative URL references are parse errors since no base URL is provided.
+    /// In particular, relative URL references are parse errors since no base URL is provided,
+    /// and input larger than `ParseLimits::recommended()` is rejected.
     #[inline]
     pub fn parse(input: &str) -> ParseResult<Url> {
         UrlParser::new().parse(input)
@@ -546,6 +717,19 @@ impl Url {
         }
     }
 
+    /// Return a normalized copy of this URL. See the `normalize` module.
+    #[inline]
+    pub fn normalize(&self, options: normalize::NormalizeOptions) -> Url {
+        normalize::normalize(self, options)
+    }
+
+    /// Return whether this URL and `other` are the same after normalization.
+    /// See the `normalize` module.
+    #[inline]
+    pub fn equivalent(&self, other: &Url, options: normalize::NormalizeOptions) -> bool {
+        normalize::equivalent(self, other, options)
+    }
+
     /// Return the serialization of this URL as a string.
     pub fn serialize(&self) -> String {
         self.to_string()
@@ -556,6 +740,34 @@ impl Url {
         UrlNoFragmentFormatter{ url: self }.to_string()
     }
 
+    /// Return the serialization of this URL together with the position of its components,
+    /// which can be sliced with ranges of `Position`s.
+    ///
+    /// An `Url` does not store its serialization, so this allocates a new string.
+    /// Keep the result to take several slices of the same URL.
+    #[inline]
+    pub fn to_serialized(&self) -> SerializedUrl {
+        SerializedUrl::new(self)
+    }
+
+    /// Return the byte index of `position` in the serialization of this URL,
+    /// without allocating the serialization.
+    #[inline]
+    pub fn position(&self, position: Position) -> usize {
+        position::offsets(self)[position as usize]
+    }
+
+    /// Return the range of byte indices between `start` and `end` in the serialization
+    /// of this URL, without allocating the serialization.
+    /// For example, `range(BeforeHost, AfterHost)` gives the host to highlight
+    /// in the displayed URL.
+    ///
+    /// If `end` is before `start`, the range is empty.
+    #[inline]
+    pub fn range(&self, start: Position, end: Position) -> Range<usize> {
+        position::range(&position::offsets(self), start, end)
+    }
+
     /// If the URL is *non-relative*, return the string scheme data.
     #[inline]
     pub fn non_relative_scheme_data<'a>(&'a self) -> Option<&'a str> {
@@ -710,6 +922,65 @@ impl Url {
         self.relative_scheme_data_mut().map(|scheme_data| &mut scheme_data.path)
     }
 
+    /// If the URL is in a *relative scheme*, return an iterator of its percent-encoded
+    /// path segments.
+    #[inline]
+    pub fn path_segments(&self) -> Option<PathSegments> {
+        self.relative_scheme_data().map(|scheme_data| {
+            path_segments::path_segments(scheme_data.path.as_slice())
+        })
+    }
+
+    /// If the URL is in a *relative scheme*, return an iterator of its percent-decoded
+    /// path segments.
+    ///
+    /// This is “lossy”: invalid UTF-8 percent-encoded byte sequences
+    /// will be replaced � U+FFFD, the replacement character.
+    #[inline]
+    pub fn lossy_percent_decode_path_segments(&self) -> Option<LossyDecodedPathSegments> {
+        self.relative_scheme_data().map(|scheme_data| {
+            path_segments::lossy_decoded_path_segments(scheme_data.path.as_slice())
+        })
+    }
+
+    /// If the URL is in a *relative scheme*, return a guard to add or remove path segments,
+    /// which percent-encodes them as needed.
+    #[inline]
+    pub fn path_segments_mut(&mut self) -> Option<PathSegmentsMut> {
+        self.relative_scheme_data_mut().map(|scheme_data| {
+            path_segments::path_segments_mut(&mut scheme_data.path)
+        })
+    }
+
+    /// If the URL is in a *relative scheme*, split each of its path segments
+    /// into a name and matrix parameters.
+    ///
+    /// See the `matrix` module.
+    #[inline]
+    pub fn matrix_segments(&self) -> Option<Vec<matrix::MatrixSegment>> {
+        self.relative_scheme_data().map(|scheme_data| {
+            scheme_data.path.iter().map(|segment| {
+                matrix::MatrixSegment::parse(segment.as_slice())
+            }).collect()
+        })
+    }
+
+    /// If the URL is in a *relative scheme*, replace its path with the given segments.
+    /// Return `Err(CannotSetPathWithNonRelativeScheme)` otherwise.
+    ///
+    /// Segments that were returned by `matrix_segments` and not modified
+    /// are serialized as they were.
+    pub fn set_matrix_segments(&mut self, segments: &[matrix::MatrixSegment])
+                               -> ParseResult<()> {
+        match self.relative_scheme_data_mut() {
+            Some(scheme_data) => {
+                scheme_data.path = segments.iter().map(|segment| segment.serialize()).collect();
+                Ok(())
+            },
+            None => Err(ParseError::CannotSetPathWithNonRelativeScheme),
+        }
+    }
+
     /// If the URL is in a *relative scheme*, serialize its path as a string.
     ///
     /// The returned string starts with a "/" slash, and components are separated by slashes.
@@ -719,6 +990,77 @@ impl Url {
         self.relative_scheme_data().map(|scheme_data| scheme_data.serialize_path())
     }
 
+    /// If the URL is in a *relative scheme*, return the request-target for an HTTP request
+    /// sent directly to the origin server: the path and query string,
+    /// without the fragment identifier.
+    ///
+    /// This is the `origin-form` of [RFC 7230](http://tools.ietf.org/html/rfc7230#section-5.3),
+    /// also used in the opening handshake of WebSocket connections.
+    #[inline]
+    pub fn request_target(&self) -> Option<String> {
+        self.relative_scheme_data().map(|scheme_data| {
+            let mut target = scheme_data.serialize_path();
+            if let Some(ref query) = self.query {
+                target.push_str("?");
+                target.push_str(query.as_slice());
+            }
+            target
+        })
+    }
+
+    /// Return the request-target for an HTTP request sent through a proxy:
+    /// the whole URL without the fragment identifier.
+    ///
+    /// This is the `absolute-form` of [RFC 7230](http://tools.ietf.org/html/rfc7230#section-5.3).
+    #[inline]
+    pub fn proxy_request_target(&self) -> String {
+        self.serialize_no_fragment()
+    }
+
+    /// If the URL is in a *relative scheme* that is not a file-like,
+    /// return the request-target for an HTTP `CONNECT` request:
+    /// the host and port number, even if it is the default.
+    ///
+    /// This is the `authority-form` of [RFC 7230](http://tools.ietf.org/html/rfc7230#section-5.3).
+    #[inline]
+    pub fn connect_request_target(&self) -> Option<String> {
+        self.relative_scheme_data().and_then(|scheme_data| {
+            scheme_data.port_or_default().map(|port| format!("{}:{}", scheme_data.host, port))
+        })
+    }
+
+    /// If the URL is in a *relative scheme* and has a non-empty host,
+    /// return the value of the `Host` header for an HTTP request:
+    /// the host, followed by the port number if it is not the default.
+    pub fn host_header(&self) -> Option<String> {
+        self.relative_scheme_data().and_then(|scheme_data| {
+            if scheme_data.domain() == Some("") {
+                return None
+            }
+            Some(match scheme_data.port {
+                Some(port) if Some(port) != scheme_data.default_port => {
+                    format!("{}:{}", scheme_data.host, port)
+                },
+                _ => scheme_data.host.serialize(),
+            })
+        })
+    }
+
+    /// If the URL is in a *relative scheme* and has a non-empty host,
+    /// return the `:scheme`, `:authority` and `:path` pseudo-header fields
+    /// for an HTTP/2 request.
+    #[inline]
+    pub fn http2_pseudo_headers(&self) -> Option<http::PseudoHeaders> {
+        match (self.host_header(), self.request_target()) {
+            (Some(authority), Some(path)) => Some(http::PseudoHeaders {
+                scheme: self.scheme.clone(),
+                authority: authority,
+                path: path,
+            }),
+            _ => None,
+        }
+    }
+
     /// Parse the URL’s query string, if any, as `application/x-www-form-urlencoded`
     /// and return a vector of (key, value) pairs.
     #[inline]
@@ -726,6 +1068,16 @@ impl Url {
         self.query.as_ref().map(|query| form_urlencoded::parse(query.as_bytes()))
     }
 
+    /// Return a lazy iterator of the (key, value) pairs of the URL’s query string, if any,
+    /// parsed as `application/x-www-form-urlencoded`.
+    ///
+    /// Unlike `query_pairs`, names and values borrow from the query string
+    /// when they do not need to be decoded.
+    #[inline]
+    pub fn query_pairs_iter(&self) -> Option<form_urlencoded::ParseIter> {
+        self.query.as_ref().map(|query| form_urlencoded::parse_iter(query.as_bytes()))
+    }
+
     /// Serialize an iterator of (key, value) pairs as `application/x-www-form-urlencoded`
     /// and set it as the URL’s query string.
     #[inline]
@@ -733,6 +1085,102 @@ impl Url {
         self.query = Some(form_urlencoded::serialize(pairs));
     }
 
+    /// Decode the URL’s query string, parsed as `application/x-www-form-urlencoded`,
+    /// into a value of a type that implements `Decodable`, such as a struct.
+    ///
+    /// A missing query string is decoded like an empty one.
+    /// See the `form_serialize` module for how fields are mapped to (key, value) pairs.
+    #[inline]
+    pub fn query_as<T: rustc_serialize::Decodable>(&self)
+                    -> Result<T, form_serialize::DecodeError> {
+        form_serialize::decode_pairs(self.query_pairs().unwrap_or_else(Vec::new))
+    }
+
+    /// Encode a value of a type that implements `Encodable`, such as a struct,
+    /// as `application/x-www-form-urlencoded` and set it as the URL’s query string.
+    ///
+    /// The query string is unchanged if encoding fails.
+    #[inline]
+    pub fn set_query_as<T: rustc_serialize::Encodable>(&mut self, value: &T)
+                        -> Result<(), form_serialize::EncodeError> {
+        self.query = Some(try!(form_serialize::encode(value)));
+        Ok(())
+    }
+
+    /// Return a guard to add, change, remove or sort the (key, value) pairs
+    /// of the URL’s query string.
+    ///
+    /// The query string is re-serialized as `application/x-www-form-urlencoded`
+    /// when the guard is dropped, if it was changed.
+    #[inline]
+    pub fn query_pairs_mut(&mut self) -> QueryPairsMut {
+        query_pairs::query_pairs_mut(self)
+    }
+
+    /// Parse the URL’s query string, if any, as `application/x-www-form-urlencoded`
+    /// and return a vector of (key, value) pairs,
+    /// decoding names and values with the given character encoding rather than UTF-8.
+    ///
+    /// Use the encoding that was given to `UrlParser::query_encoding_override`
+    /// when parsing the URL. `Url` does not record it, since its fields are public
+    /// and it is compared by value.
+    ///
+    /// This method is only available if the `query_encoding` Cargo feature is enabled.
+    #[cfg(feature = "query_encoding")]
+    #[inline]
+    pub fn query_pairs_with_encoding(&self, encoding: encoding::EncodingRef)
+                                     -> Option<Vec<(String, String)>> {
+        self.query.as_ref().and_then(|query| {
+            form_urlencoded::parse_with_encoding(query.as_bytes(), Some(encoding), false)
+        })
+    }
+
+    /// Percent-decode the URL’s query string, if any,
+    /// and decode the result with the given character encoding rather than UTF-8.
+    ///
+    /// This is “lossy”: invalid byte sequences will be replaced � U+FFFD,
+    /// the replacement character.
+    ///
+    /// This method is only available if the `query_encoding` Cargo feature is enabled.
+    #[cfg(feature = "query_encoding")]
+    #[inline]
+    pub fn lossy_percent_decode_query_with_encoding(&self, encoding: encoding::EncodingRef)
+                                                    -> Option<String> {
+        self.query.as_ref().map(|value| {
+            EncodingOverride::from_encoding(encoding).decode(
+                percent_decode(value.as_bytes()).as_slice())
+        })
+    }
+
+    /// Like `query_as`, but decode names and values with the given character encoding
+    /// rather than UTF-8.
+    ///
+    /// Use the encoding that was given to `UrlParser::query_encoding_override`
+    /// when parsing the URL.
+    ///
+    /// This method is only available if the `query_encoding` Cargo feature is enabled.
+    #[cfg(feature = "query_encoding")]
+    #[inline]
+    pub fn query_as_with_encoding<T: rustc_serialize::Decodable>(
+                                  &self, encoding: encoding::EncodingRef)
+                                  -> Result<T, form_serialize::DecodeError> {
+        let query = self.query.as_ref().map_or("", |query| query.as_slice());
+        form_serialize::decode_with_encoding(query.as_bytes(), Some(encoding), false)
+    }
+
+    /// Like `set_query_as`, but encode names and values with the given character encoding
+    /// rather than UTF-8, as `UrlParser::query_encoding_override` does.
+    ///
+    /// This method is only available if the `query_encoding` Cargo feature is enabled.
+    #[cfg(feature = "query_encoding")]
+    #[inline]
+    pub fn set_query_as_with_encoding<T: rustc_serialize::Encodable>(
+                                      &mut self, value: &T, encoding: encoding::EncodingRef)
+                                      -> Result<(), form_serialize::EncodeError> {
+        self.query = Some(try!(form_serialize::encode_with_encoding(value, Some(encoding))));
+        Ok(())
+    }
+
     /// Percent-decode the URL’s query string, if any.
     ///
     /// This is “lossy”: invalid UTF-8 percent-encoded byte sequences
@@ -0,0 +1,242 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Matrix parameters in path segments, as in `/cars;color=red;year=2015/engine`.
+//!
+//! [RFC 3986](http://tools.ietf.org/html/rfc3986#section-3.3) allows path segments
+//! to contain `;`-delimited parameters, whose syntax is left to each scheme or application.
+//! This module uses the common `name;key=value;key=value` convention,
+//! as used for example by JAX-RS’s `@MatrixParam`.
+//!
+//! A `MatrixSegment` keeps the percent-encoded pieces of the segment it was parsed from,
+//! so that serializing it again gives the same string unless it was modified.
+//!
+//! ```
+//! use url::Url;
+//!
+//! let mut url = Url::parse("http://example.com/cars;color=red;year=2015/engine").unwrap();
+//! let mut segments = url.matrix_segments().unwrap();
+//! assert_eq!(segments[0].name(), "cars".to_string());
+//! assert_eq!(segments[0].get("color"), Some("red".to_string()));
+//!
+//! segments[0].set("color", "dark blue");
+//! url.set_matrix_segments(segments.as_slice()).unwrap();
+//! assert_eq!(url.serialize(),
+//!            "http://example.com/cars;color=dark%20blue;year=2015/engine".to_string());
+//! ```
+
+use percent_encoding::{utf8_percent_encode, lossy_utf8_percent_decode, MATRIX_PARAM_ENCODE_SET};
+
+
+/// A path segment split into a name and an ordered list of matrix parameters.
+///
+/// Each parameter is a key and an optional value:
+/// `None` for a parameter without `=`, like `flag` in `name;flag`.
+/// Keys can be repeated.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct MatrixSegment {
+    name: String,
+    params: Vec<(String, Option<String>)>,
+}
+
+
+impl MatrixSegment {
+    /// Return a segment with the given name, percent-encoded, and no parameter.
+    #[inline]
+    pub fn new(name: &str) -> MatrixSegment {
+        MatrixSegment { name: encode(name), params: Vec::new() }
+    }
+
+    /// Split an percent-encoded path segment at `;` and at the first `=` of each parameter.
+    ///
+    /// The pieces are kept as-is, and are only percent-decoded by the accessor methods.
+    pub fn parse(segment: &str) -> MatrixSegment {
+        let mut pieces = segment.split(';');
+        let name = pieces.next().unwrap_or("").to_string();
+        let params = pieces.map(|param| match param.find('=') {
+            Some(position) => (param[..position].to_string(),
+                               Some(param[position + 1..].to_string())),
+            None => (param.to_string(), None),
+        }).collect();
+        MatrixSegment { name: name, params: params }
+    }
+
+    /// Return the percent-encoded path segment.
+    ///
+    /// For a segment returned by `parse` and not modified since,
+    /// this is the same as the input of `parse`.
+    pub fn serialize(&self) -> String {
+        let mut result = self.name.clone();
+        for &(ref key, ref value) in self.params.iter() {
+            result.push(';');
+            result.push_str(key.as_slice());
+            if let Some(ref value) = *value {
+                result.push('=');
+                result.push_str(value.as_slice());
+            }
+        }
+        result
+    }
+
+    /// Return the percent-encoded name of the segment, the part before the first `;`.
+    #[inline]
+    pub fn raw_name(&self) -> &str {
+        self.name.as_slice()
+    }
+
+    /// Percent-decode the name of the segment.
+    ///
+    /// This is “lossy”: invalid UTF-8 percent-encoded byte sequences
+    /// will be replaced � U+FFFD, the replacement character.
+    #[inline]
+    pub fn name(&self) -> String {
+        lossy_utf8_percent_decode(self.name.as_bytes())
+    }
+
+    /// Percent-encode and set the name of the segment, and return the segment.
+    #[inline]
+    pub fn set_name(&mut self, name: &str) -> &mut MatrixSegment {
+        self.name = encode(name);
+        self
+    }
+
+    /// Return the percent-encoded (key, value) parameters, in order.
+    #[inline]
+    pub fn raw_params(&self) -> &[(String, Option<String>)] {
+        self.params.as_slice()
+    }
+
+    /// Return the percent-decoded (key, value) parameters, in order.
+    ///
+    /// This is “lossy”: invalid UTF-8 percent-encoded byte sequences
+    /// will be replaced � U+FFFD, the replacement character.
+    pub fn params(&self) -> Vec<(String, Option<String>)> {
+        self.params.iter().map(|&(ref key, ref value)| {
+            (lossy_utf8_percent_decode(key.as_bytes()),
+             value.as_ref().map(|value| lossy_utf8_percent_decode(value.as_bytes())))
+        }).collect()
+    }
+
+    /// Return the percent-decoded value of the first parameter with the given key, if any.
+    ///
+    /// A parameter without `=` has an empty value.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.params.iter().find(|&&(ref k, _)| key_matches(k.as_slice(), key))
+                   .map(|&(_, ref value)| decode_value(value))
+    }
+
+    /// Return the percent-decoded values of all parameters with the given key, in order.
+    pub fn get_all(&self, key: &str) -> Vec<String> {
+        self.params.iter().filter(|&&(ref k, _)| key_matches(k.as_slice(), key))
+                   .map(|&(_, ref value)| decode_value(value)).collect()
+    }
+
+    /// Return whether there is a parameter with the given key.
+    #[inline]
+    pub fn has(&self, key: &str) -> bool {
+        self.params.iter().any(|&(ref k, _)| key_matches(k.as_slice(), key))
+    }
+
+    /// Percent-encode and add a parameter at the end, even if there is already one
+    /// with the same key, and return the segment.
+    pub fn append(&mut self, key: &str, value: &str) -> &mut MatrixSegment {
+        self.params.push((encode(key), Some(encode(value))));
+        self
+    }
+
+    /// Set the value of the first parameter with the given key,
+    /// remove the other parameters with that key, and return the segment.
+    /// If there is no such parameter, add one at the end.
+    ///
+    /// Only the edited parameter is re-encoded: other parameters are left unchanged.
+    pub fn set(&mut self, key: &str, value: &str) -> &mut MatrixSegment {
+        let mut found = false;
+        let mut i = 0;
+        while i < self.params.len() {
+            if key_matches(self.params[i].0.as_slice(), key) {
+                if found {
+                    self.params.remove(i);
+                    continue
+                }
+                self.params[i].1 = Some(encode(value));
+                found = true;
+            }
+            i += 1;
+        }
+        if !found {
+            self.params.push((encode(key), Some(encode(value))));
+        }
+        self
+    }
+
+    /// Remove all parameters with the given key, and return the segment.
+    pub fn remove(&mut self, key: &str) -> &mut MatrixSegment {
+        self.params.retain(|&(ref k, _)| !key_matches(k.as_slice(), key));
+        self
+    }
+
+    /// Remove all parameters, and return the segment.
+    #[inline]
+    pub fn clear_params(&mut self) -> &mut MatrixSegment {
+        self.params.truncate(0);
+        self
+    }
+}
+
+
+#[inline]
+fn encode(input: &str) -> String {
+    utf8_percent_encode(input, MATRIX_PARAM_ENCODE_SET)
+}
+
+/// Compare a percent-encoded key with a decoded one.
+/// `key` is never compared with `raw_key` directly: `b%3Bc` names the key `b;c`, not `b%3Bc`.
+#[inline]
+fn key_matches(raw_key: &str, key: &str) -> bool {
+    lossy_utf8_percent_decode(raw_key.as_bytes()) == key
+}
+
+#[inline]
+fn decode_value(value: &Option<String>) -> String {
+    match *value {
+        Some(ref value) => lossy_utf8_percent_decode(value.as_bytes()),
+        None => String::new(),
+    }
+}
+
+
+#[test]
+fn test_matrix_segment() {
+    for &input in ["", "bar", "bar;par", "a;b=c=d;;e=;f", ";x=1", "a%3Bb;k%3D=v%3B"].iter() {
+        assert_eq!(MatrixSegment::parse(input).serialize(), input.to_string());
+    }
+
+    let mut segment = MatrixSegment::parse("a%3Bb;k%3D=v%3B;flag;k%3D=2");
+    assert_eq!(segment.name(), "a;b".to_string());
+    assert_eq!(segment.get("k="), Some("v;".to_string()));
+    assert_eq!(segment.get_all("k="), vec!["v;".to_string(), "2".to_string()]);
+    assert_eq!(segment.get("flag"), Some("".to_string()));
+    assert_eq!(segment.get("missing"), None);
+    assert_eq!(segment.get("k%3D"), None);
+    assert!(!segment.has("k%3D"));
+    assert_eq!(segment.params(), vec![
+        ("k=".to_string(), Some("v;".to_string())),
+        ("flag".to_string(), None),
+        ("k=".to_string(), Some("2".to_string())),
+    ]);
+
+    segment.set("k=", "x=y;z").remove("flag").append("é", "/");
+    assert_eq!(segment.serialize(), "a%3Bb;k%3D=x%3Dy%3Bz;%C3%A9=%2F".to_string());
+    // Setting an encoded-looking key adds a new parameter rather than overwriting `k=`.
+    segment.set("k%3D", "1");
+    assert_eq!(segment.serialize(),
+               "a%3Bb;k%3D=x%3Dy%3Bz;%C3%A9=%2F;k%253D=1".to_string());
+    segment.set_name("c d").clear_params();
+    assert_eq!(segment.serialize(), "c%20d".to_string());
+    assert_eq!(MatrixSegment::new("e;f").serialize(), "e%3Bf".to_string());
+}
@@ -0,0 +1,219 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! [Normalization and comparison](http://tools.ietf.org/html/rfc3986#section-6)
+//! of URLs, to decide whether two URLs identify the same resource.
+//!
+//! The syntax-based and scheme-based normalizations of RFC 3986 are always applied:
+//!
+//! * The scheme and host are in lower case. (The parser already does this.)
+//! * The hexadecimal digits of percent-encoded bytes are in upper case.
+//! * Percent-encoded unreserved characters (`A-Z a-z 0-9 - . _ ~`) are decoded.
+//! * `.` and `..` path segments are removed, including those that were percent-encoded.
+//! * The default port of the scheme is removed.
+//! * An empty path becomes `/`.
+//!
+//! These do not change the meaning of an URL.
+//! The protocol-based steps of `NormalizeOptions` are common heuristics that can,
+//! and are only applied when enabled.
+//!
+//! ```
+//! use url::Url;
+//! use url::normalize::NormalizeOptions;
+//!
+//! let a = Url::parse("HTTP://www.Example.com:80/%7euser/./a/../b?y=2&x=1").unwrap();
+//! let b = Url::parse("http://example.com/~user/b/?x=1&y=2#top").unwrap();
+//! assert_eq!(a.normalize(NormalizeOptions::rfc3986()).serialize(),
+//!            "http://www.example.com/~user/b?y=2&x=1".to_string());
+//! assert!(!a.equivalent(&b, NormalizeOptions::rfc3986()));
+//! assert!(a.equivalent(&b, NormalizeOptions::aggressive()));
+//! ```
+
+use std::ascii::AsciiExt;
+use super::{Url, SchemeData, Host};
+use percent_encoding::from_hex;
+
+
+/// The optional, protocol-based normalization steps.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct NormalizeOptions {
+    /// Remove the trailing slash of a non-empty path, as in `/a/b/` to `/a/b`.
+    pub remove_trailing_slash: bool,
+
+    /// Remove a `www.` prefix from the domain, unless nothing else would be left.
+    pub remove_www: bool,
+
+    /// Sort the `&`-separated pairs of the query string by name.
+    /// Pairs with the same name keep their relative order.
+    pub sort_query: bool,
+
+    /// Remove an empty query string, as in `http://example.com/?` to `http://example.com/`.
+    pub remove_empty_query: bool,
+
+    /// Remove the fragment identifier.
+    pub remove_fragment: bool,
+}
+
+
+impl NormalizeOptions {
+    /// No protocol-based step:
+    /// only the syntax-based and scheme-based normalizations of RFC 3986.
+    pub fn rfc3986() -> NormalizeOptions {
+        NormalizeOptions {
+            remove_trailing_slash: false,
+            remove_www: false,
+            sort_query: false,
+            remove_empty_query: false,
+            remove_fragment: false,
+        }
+    }
+
+    /// All protocol-based steps, for example to detect duplicates in a set of links.
+    pub fn aggressive() -> NormalizeOptions {
+        NormalizeOptions {
+            remove_trailing_slash: true,
+            remove_www: true,
+            sort_query: true,
+            remove_empty_query: true,
+            remove_fragment: true,
+        }
+    }
+}
+
+
+/// Return a normalized copy of `url`. See also `Url::normalize`.
+pub fn normalize(url: &Url, options: NormalizeOptions) -> Url {
+    let mut url = url.clone();
+    url.scheme = url.scheme.as_slice().to_ascii_lowercase();
+    match url.scheme_data {
+        SchemeData::NonRelative(ref mut scheme_data) => {
+            let normalized = normalize_percent_encoding(scheme_data.as_slice());
+            *scheme_data = normalized;
+        },
+        SchemeData::Relative(ref mut scheme_data) => {
+            scheme_data.username = normalize_percent_encoding(scheme_data.username.as_slice());
+            scheme_data.password = scheme_data.password.take().map(|password| {
+                normalize_percent_encoding(password.as_slice())
+            });
+            if let Host::Domain(ref mut domain) = scheme_data.host {
+                let mut normalized = domain.as_slice().to_ascii_lowercase();
+                if options.remove_www && normalized.starts_with("www.") && normalized.len() > 4 {
+                    normalized = normalized[4..].to_string();
+                }
+                *domain = normalized;
+            }
+            if scheme_data.port.is_some() && scheme_data.port == scheme_data.default_port {
+                scheme_data.port = None;
+            }
+            let segments = scheme_data.path.iter().map(|segment| {
+                normalize_percent_encoding(segment.as_slice())
+            }).collect();
+            scheme_data.path = remove_dot_segments(segments);
+            if options.remove_trailing_slash && scheme_data.path.len() > 1 &&
+                    scheme_data.path.last().map_or(false, |segment| segment.is_empty()) {
+                scheme_data.path.pop();
+            }
+        },
+    }
+    url.query = url.query.take().and_then(|query| {
+        let query = normalize_percent_encoding(query.as_slice());
+        if options.remove_empty_query && query.is_empty() {
+            None
+        } else if options.sort_query {
+            Some(sort_query(query.as_slice()))
+        } else {
+            Some(query)
+        }
+    });
+    url.fragment = if options.remove_fragment {
+        None
+    } else {
+        url.fragment.take().map(|fragment| normalize_percent_encoding(fragment.as_slice()))
+    };
+    url
+}
+
+
+/// Return whether `a` and `b` are the same after normalization.
+/// See also `Url::equivalent`.
+#[inline]
+pub fn equivalent(a: &Url, b: &Url, options: NormalizeOptions) -> bool {
+    normalize(a, options) == normalize(b, options)
+}
+
+
+/// Decode percent-encoded unreserved characters,
+/// and use upper case hexadecimal digits for other percent-encoded bytes.
+pub fn normalize_percent_encoding(input: &str) -> String {
+    let input = input.as_bytes();
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let byte = input[i];
+        if byte == b'%' && i + 2 < input.len() {
+            if let (Some(h), Some(l)) = (from_hex(input[i + 1]), from_hex(input[i + 2])) {
+                let decoded = h * 0x10 + l;
+                if is_unreserved(decoded) {
+                    output.push(decoded);
+                } else {
+                    output.push(b'%');
+                    output.push(input[i + 1].to_ascii_uppercase());
+                    output.push(input[i + 2].to_ascii_uppercase());
+                }
+                i += 3;
+                continue
+            }
+        }
+        output.push(byte);
+        i += 1;
+    }
+    // Only ASCII sequences were replaced by ASCII bytes.
+    String::from_utf8(output).unwrap()
+}
+
+
+/// Remove `.` and `..` segments from a path that was normalized,
+/// keeping a trailing slash when the last segment was one of them.
+fn remove_dot_segments(segments: Vec<String>) -> Vec<String> {
+    let length = segments.len();
+    let mut output: Vec<String> = Vec::with_capacity(length);
+    for (i, segment) in segments.into_iter().enumerate() {
+        let is_dot = segment.as_slice() == ".";
+        let is_dot_dot = segment.as_slice() == "..";
+        if is_dot_dot {
+            output.pop();
+        } else if !is_dot {
+            output.push(segment);
+            continue
+        }
+        if i + 1 == length {
+            output.push(String::new());
+        }
+    }
+    if output.is_empty() {
+        output.push(String::new());
+    }
+    output
+}
+
+
+fn sort_query(query: &str) -> String {
+    let mut pairs: Vec<&str> = query.split('&').collect();
+    pairs.sort_by(|a, b| {
+        let name_a = &a[..a.find('=').unwrap_or(a.len())];
+        let name_b = &b[..b.find('=').unwrap_or(b.len())];
+        name_a.cmp(name_b)
+    });
+    pairs.connect("&")
+}
+
+
+#[inline]
+fn is_unreserved(byte: u8) -> bool {
+    matches!(byte, b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~')
+}
@@ -9,11 +9,13 @@
 use std::ascii::AsciiExt;
 use std::error::Error;
 use std::fmt::{self, Formatter};
+use std::mem;
 use std::str::CharRange;
 
 use super::{UrlParser, Url, SchemeData, RelativeSchemeData, Host, SchemeType};
+use scan::{self, ByteSet};
 use percent_encoding::{
-    utf8_percent_encode_to, percent_encode,
+    utf8_percent_encode_to, percent_encode, percent_encode_to, EncodeSet,
     SIMPLE_ENCODE_SET, DEFAULT_ENCODE_SET, USERINFO_ENCODE_SET, QUERY_ENCODE_SET
 };
 
@@ -21,6 +23,65 @@ use percent_encoding::{
 pub type ParseResult<T> = Result<T, ParseError>;
 
 
+/// The allocations of the previous value of an `Url`, reused by `parse_url_into`.
+/// Parsing without one uses `Buffers::new()`, which hands out new strings and paths.
+struct Buffers {
+    strings: Vec<String>,
+    path: Option<Vec<String>>,
+}
+
+
+impl Buffers {
+    #[inline]
+    fn new() -> Buffers {
+        Buffers { strings: Vec::new(), path: None }
+    }
+
+    /// Take the strings and the path of `url`.
+    fn from_url(url: Url) -> Buffers {
+        let Url { scheme, scheme_data, query, fragment } = url;
+        let mut buffers = Buffers::new();
+        buffers.strings.push(scheme);
+        match scheme_data {
+            SchemeData::Relative(RelativeSchemeData { username, password, host, mut path, .. }) => {
+                buffers.strings.push(username);
+                buffers.strings.extend(password.into_iter());
+                if let Host::Domain(domain) = host {
+                    buffers.strings.push(domain);
+                }
+                buffers.strings.extend(path.drain());
+                buffers.path = Some(path);
+            },
+            SchemeData::NonRelative(scheme_data) => buffers.strings.push(scheme_data),
+        }
+        buffers.strings.extend(query.into_iter());
+        buffers.strings.extend(fragment.into_iter());
+        buffers
+    }
+
+    /// Return an empty string, reusing an allocation if any is left.
+    #[inline]
+    fn string(&mut self) -> String {
+        match self.strings.pop() {
+            Some(mut string) => {
+                string.clear();
+                string
+            },
+            None => String::new(),
+        }
+    }
+
+    /// Return an empty path, reusing the allocation of the previous path if any.
+    #[inline]
+    fn path(&mut self) -> Vec<String> {
+        match self.path.take() {
+            Some(path) => path,
+            None => Vec::new(),
+        }
+    }
+}
+
+
 macro_rules! simple_enum_error {
     ($($name: ident => $description: expr,)+) => {
         /// Errors that can occur during parsing.
@@ -68,6 +129,25 @@ simple_enum_error! {
     CannotSetHostWithNonRelativeScheme => "cannot set host with non-relative scheme",
     CannotSetPortWithNonRelativeScheme => "cannot set port with non-relative scheme",
     CannotSetPathWithNonRelativeScheme => "cannot set path with non-relative scheme",
+    InvalidUserinfoCharacter => "invalid character in userinfo",
+    InvalidPathCharacter => "invalid character in path",
+    InvalidQueryCharacter => "invalid character in query",
+    InvalidFragmentCharacter => "invalid character in fragment",
+    UnsupportedIpFuture => "IPvFuture addresses are not supported",
+    UrlTooLong => "URL too long",
+    HostTooLong => "host too long",
+    TooManyPathSegments => "too many path segments",
+    PathSegmentTooLong => "path segment too long",
+    DotPathSegment => "path segment is . or ..",
+    QueryTooLong => "query string too long",
+    TooManyFormPairs => "too many name-value pairs",
+    FormNameTooLong => "name too long",
+    FormValueTooLong => "value too long",
+    InvalidUtf8InScheme => "invalid UTF-8 in scheme",
+    InvalidUtf8InHost => "invalid UTF-8 in host",
+    InvalidRequestTarget => "invalid HTTP request-target",
+    InvalidHostHeader => "invalid HTTP Host header",
+    InvalidSurt => "invalid SURT",
 }
 
 impl fmt::Display for ParseError {
@@ -92,8 +172,61 @@ pub enum Context {
 }
 
 
+#[inline]
 pub fn parse_url(input: &str, parser: &UrlParser) -> ParseResult<Url> {
-    let input = input.trim_matches([' ', '\t', '\n', '\r', '\x0C'].as_slice());
+    parse_url_with_length(input, input.len(), parser, &mut Buffers::new())
+}
+
+
+/// Parse `input` into `url`, reusing the strings and vectors of its previous value.
+/// If parsing fails, `url` is set to `about:blank`.
+pub fn parse_url_into(input: &str, parser: &UrlParser, url: &mut Url) -> ParseResult<()> {
+    // Empty strings do not allocate.
+    let previous = mem::replace(url, Url {
+        scheme: String::new(),
+        scheme_data: SchemeData::NonRelative(String::new()),
+        query: None,
+        fragment: None,
+    });
+    let mut buffers = Buffers::from_url(previous);
+    match parse_url_with_length(input, input.len(), parser, &mut buffers) {
+        Ok(new_url) => {
+            *url = new_url;
+            Ok(())
+        },
+        Err(error) => {
+            // The previous value can not be restored: some of its strings were overwritten.
+            *url = about_blank(&mut buffers);
+            Err(error)
+        },
+    }
+}
+
+
+/// Return the `about:blank` URL, reusing allocations from `buffers`.
+fn about_blank(buffers: &mut Buffers) -> Url {
+    let mut scheme = buffers.string();
+    scheme.push_str("about");
+    let mut scheme_data = buffers.string();
+    scheme_data.push_str("blank");
+    Url {
+        scheme: scheme,
+        scheme_data: SchemeData::NonRelative(scheme_data),
+        query: None,
+        fragment: None,
+    }
+}
+
+
+/// Like `parse_url`, but check `max_length` against `input_length`
+/// rather than the length of `input`.
+fn parse_url_with_length(input: &str, input_length: usize, parser: &UrlParser,
+                         buffers: &mut Buffers) -> ParseResult<Url> {
+    try!(check_limit(input_length, parser.limits.max_length, ParseError::UrlTooLong));
+    if parser.strict_rfc3986 {
+        try!(::rfc3986::validate(input, parser.base_url.is_some()));
+    }
+    let input = trim_input(input);
     let (scheme, remaining) = match parse_scheme(input, Context::UrlParser) {
         Some((scheme, remaining)) => (scheme, remaining),
         // No-scheme state
@@ -101,7 +234,8 @@ pub fn parse_url(input: &str, parser: &UrlParser) -> ParseResult<Url> {
             Some(&Url { ref scheme, scheme_data: SchemeData::Relative(ref base),
                         ref query, .. }) => {
                 let scheme_type = parser.get_scheme_type(scheme.as_slice());
-                parse_relative_url(input, scheme.clone(), scheme_type, base, query, parser)
+                parse_relative_url(input, scheme.clone(), scheme_type, base, query, parser,
+                                   buffers)
             },
             Some(_) => Err(ParseError::RelativeUrlWithNonRelativeBase),
             None => Err(ParseError::RelativeUrlWithoutBase),
@@ -115,13 +249,14 @@ pub fn parse_url(input: &str, parser: &UrlParser) -> ParseResult<Url> {
                 Some(&Url { scheme: ref base_scheme, scheme_data: SchemeData::Relative(ref base),
                             ref query, .. })
                 if scheme == *base_scheme => {
-                    parse_relative_url(remaining, scheme, scheme_type, base, query, parser)
+                    parse_relative_url(remaining, scheme, scheme_type, base, query, parser,
+                                       buffers)
                 },
                 // FIXME: Should not have to use a made-up base URL.
                 _ => parse_relative_url(remaining, scheme, scheme_type, &RelativeSchemeData {
                     username: String::new(), password: None, host: Host::Domain(String::new()),
                     port: None, default_port: None, path: Vec::new()
-                }, &None, parser)
+                }, &None, parser, buffers)
             }
         },
         SchemeType::Relative(..) => {
@@ -130,15 +265,16 @@ pub fn parse_url(input: &str, parser: &UrlParser) -> ParseResult<Url> {
                             ref query, .. })
                 if scheme == *base_scheme && !remaining.starts_with("//") => {
                     try!(parser.parse_error(ParseError::RelativeUrlWithScheme));
-                    parse_relative_url(remaining, scheme, scheme_type, base, query, parser)
+                    parse_relative_url(remaining, scheme, scheme_type, base, query, parser,
+                                       buffers)
                 },
-                _ => parse_absolute_url(scheme, scheme_type, remaining, parser),
+                _ => parse_absolute_url(scheme, scheme_type, remaining, parser, buffers),
             }
         },
         SchemeType::NonRelative => {
             // Scheme data state
-            let (scheme_data, remaining) = try!(parse_scheme_data(remaining, parser));
-            let (query, fragment) = try!(parse_query_and_fragment(remaining, parser));
+            let (scheme_data, remaining) = try!(parse_scheme_data(remaining, parser, buffers));
+            let (query, fragment) = try!(parse_query_and_fragment(remaining, parser, buffers));
             Ok(Url { scheme: scheme, scheme_data: SchemeData::NonRelative(scheme_data),
                      query: query, fragment: fragment })
         }
@@ -146,6 +282,79 @@ pub fn parse_url(input: &str, parser: &UrlParser) -> ParseResult<Url> {
 }
 
 
+/// Bytes that are not valid UTF-8 are percent-encoded, then the result is parsed with `parse_url`.
+/// The `max_length` limit applies to the length of `input`, before percent-encoding.
+///
+/// In the host, these bytes are percent-decoded again by `Host::parse`,
+/// which fails with `InvalidUtf8InHost`.
+/// Since `%` can not be part of a scheme, finding them in the scheme takes another step:
+/// `parse_scheme` is run with these bytes replaced by a letter.
+pub fn parse_url_bytes(input: &[u8], parser: &UrlParser) -> ParseResult<Url> {
+    let mut string = String::new();
+    // Same as `input`, with each invalid byte replaced by a letter.
+    let mut scheme_probe = String::new();
+    let mut first_invalid = None;
+    let mut i = 0;
+    while i < input.len() {
+        match utf8_sequence_length(&input[i..]) {
+            Some(length) => {
+                let valid = ::std::str::from_utf8(&input[i..i + length]).unwrap();
+                string.push_str(valid);
+                scheme_probe.push_str(valid);
+                i += length;
+            },
+            None => {
+                percent_encode_to(&input[i..i + 1], SIMPLE_ENCODE_SET, &mut string);
+                scheme_probe.push('a');
+                if first_invalid.is_none() {
+                    first_invalid = Some(i);
+                }
+                i += 1;
+            }
+        }
+    }
+    if let Some(first_invalid) = first_invalid {
+        let trimmed = trim_input(scheme_probe.as_slice());
+        let start = trimmed.as_ptr() as usize - scheme_probe.as_ptr() as usize;
+        if let Some((scheme, _)) = parse_scheme(trimmed, Context::UrlParser) {
+            if first_invalid < start + scheme.len() {
+                return Err(ParseError::InvalidUtf8InScheme)
+            }
+        }
+    }
+    parse_url_with_length(string.as_slice(), input.len(), parser, &mut Buffers::new())
+}
+
+
+/// Remove leading and trailing spaces and control characters, as the parser does first.
+#[inline]
+fn trim_input(input: &str) -> &str {
+    input.trim_matches([' ', '\t', '\n', '\r', '\x0C'].as_slice())
+}
+
+
+/// Return the length of the well-formed UTF-8 sequence at the start of non-empty `input`, if any.
+fn utf8_sequence_length(input: &[u8]) -> Option<usize> {
+    // http://www.unicode.org/versions/Unicode7.0.0/ch03.pdf#G7404
+    let (length, second_min, second_max) = match input[0] {
+        0x00...0x7F => return Some(1),
+        0xC2...0xDF => (2, 0x80, 0xBF),
+        0xE0 => (3, 0xA0, 0xBF),
+        0xE1...0xEC | 0xEE...0xEF => (3, 0x80, 0xBF),
+        0xED => (3, 0x80, 0x9F),
+        0xF0 => (4, 0x90, 0xBF),
+        0xF1...0xF3 => (4, 0x80, 0xBF),
+        0xF4 => (4, 0x80, 0x8F),
+        _ => return None,
+    };
+    if input.len() < length || input[1] < second_min || input[1] > second_max
+            || !input[2..length].iter().all(|&b| b >= 0x80 && b <= 0xBF) {
+        return None
+    }
+    Some(length)
+}
+
+
 pub fn parse_scheme<'a>(input: &'a str, context: Context) -> Option<(String, &'a str)> {
     if input.is_empty() || !starts_with_ascii_alpha(input) {
         return None
@@ -169,15 +378,16 @@ pub fn parse_scheme<'a>(input: &'a str, context: Context) -> Option<(String, &'a
 
 
 fn parse_absolute_url<'a>(scheme: String, scheme_type: SchemeType,
-                          input: &'a str, parser: &UrlParser) -> ParseResult<Url> {
+                          input: &'a str, parser: &UrlParser, buffers: &mut Buffers)
+                          -> ParseResult<Url> {
     // Authority first slash state
     let remaining = try!(skip_slashes(input, parser));
     // Authority state
-    let (username, password, remaining) = try!(parse_userinfo(remaining, parser));
+    let (username, password, remaining) = try!(parse_userinfo(remaining, parser, buffers));
     // Host state
     let (host, port, default_port, remaining) = try!(parse_host(remaining, scheme_type, parser));
-    let (path, remaining) = try!(parse_path_start(
-        remaining, Context::UrlParser, scheme_type, parser));
+    let (path, remaining) = try!(parse_path_start_with_buffers(
+        remaining, Context::UrlParser, scheme_type, parser, buffers));
     let scheme_data = SchemeData::Relative(RelativeSchemeData {
         username: username, password: password,
         host: host, port: port, default_port: default_port,
@@ -189,7 +399,7 @@ fn parse_absolute_url<'a>(scheme: String, scheme_type: SchemeType,
 
 fn parse_relative_url<'a>(input: &'a str, scheme: String, scheme_type: SchemeType,
                           base: &RelativeSchemeData, base_query: &Option<String>,
-                          parser: &UrlParser)
+                          parser: &UrlParser, buffers: &mut Buffers)
                           -> ParseResult<Url> {
     if input.is_empty() {
         return Ok(Url { scheme: scheme, scheme_data: SchemeData::Relative(base.clone()),
@@ -217,22 +427,23 @@ fn parse_relative_url<'a>(input: &'a str, scheme: String, scheme_type: SchemeTyp
                     } else {
                         try!(parse_file_host(remaining, parser))
                     };
-                    let (path, remaining) = try!(parse_path_start(
-                        remaining, Context::UrlParser, scheme_type, parser));
+                    let (path, remaining) = try!(parse_path_start_with_buffers(
+                        remaining, Context::UrlParser, scheme_type, parser, buffers));
                     let scheme_data = SchemeData::Relative(RelativeSchemeData {
                         username: String::new(), password: None,
                         host: host, port: None, default_port: None, path: path
                     });
-                    let (query, fragment) = try!(parse_query_and_fragment(remaining, parser));
+                    let (query, fragment) = try!(
+                        parse_query_and_fragment(remaining, parser, buffers));
                     Ok(Url { scheme: scheme, scheme_data: scheme_data,
                              query: query, fragment: fragment })
                 } else {
-                    parse_absolute_url(scheme, scheme_type, input, parser)
+                    parse_absolute_url(scheme, scheme_type, input, parser, buffers)
                 }
             } else {
                 // Relative path state
                 let (path, remaining) = try!(parse_path(
-                    &[], &input[1..], Context::UrlParser, scheme_type, parser));
+                    &[], &input[1..], Context::UrlParser, scheme_type, parser, buffers));
                 let scheme_data = SchemeData::Relative(if scheme_type == SchemeType::FileLike {
                     RelativeSchemeData {
                         username: String::new(), password: None, host:
@@ -249,18 +460,18 @@ fn parse_relative_url<'a>(input: &'a str, scheme: String, scheme_type: SchemeTyp
                     }
                 });
                 let (query, fragment) = try!(
-                    parse_query_and_fragment(remaining, parser));
+                    parse_query_and_fragment(remaining, parser, buffers));
                 Ok(Url { scheme: scheme, scheme_data: scheme_data,
                          query: query, fragment: fragment })
             }
         },
         '?' => {
-            let (query, fragment) = try!(parse_query_and_fragment(input, parser));
+            let (query, fragment) = try!(parse_query_and_fragment(input, parser, buffers));
             Ok(Url { scheme: scheme, scheme_data: SchemeData::Relative(base.clone()),
                      query: query, fragment: fragment })
         },
         '#' => {
-            let fragment = Some(try!(parse_fragment(&input[1..], parser)));
+            let fragment = Some(try!(parse_fragment_with_buffers(&input[1..], parser, buffers)));
             Ok(Url { scheme: scheme, scheme_data: SchemeData::Relative(base.clone()),
                      query: base_query.clone(), fragment: fragment })
         }
@@ -274,7 +485,7 @@ fn parse_relative_url<'a>(input: &'a str, scheme: String, scheme_type: SchemeTyp
             {
                 // Windows drive letter quirk
                 let (path, remaining) = try!(parse_path(
-                    &[], input, Context::UrlParser, scheme_type, parser));
+                    &[], input, Context::UrlParser, scheme_type, parser, buffers));
                  (SchemeData::Relative(RelativeSchemeData {
                     username: String::new(), password: None,
                     host: Host::Domain(String::new()),
@@ -286,7 +497,7 @@ fn parse_relative_url<'a>(input: &'a str, scheme: String, scheme_type: SchemeTyp
                 let base_path = &base.path[..base.path.len() - 1];
                 // Relative path state
                 let (path, remaining) = try!(parse_path(
-                    base_path, input, Context::UrlParser, scheme_type, parser));
+                    base_path, input, Context::UrlParser, scheme_type, parser, buffers));
                 (SchemeData::Relative(RelativeSchemeData {
                     username: base.username.clone(),
                     password: base.password.clone(),
@@ -296,7 +507,7 @@ fn parse_relative_url<'a>(input: &'a str, scheme: String, scheme_type: SchemeTyp
                     path: path
                 }), remaining)
             };
-            let (query, fragment) = try!(parse_query_and_fragment(remaining, parser));
+            let (query, fragment) = try!(parse_query_and_fragment(remaining, parser, buffers));
             Ok(Url { scheme: scheme, scheme_data: scheme_data,
                      query: query, fragment: fragment })
         }
@@ -313,7 +524,7 @@ fn skip_slashes<'a>(input: &'a str, parser: &UrlParser) -> ParseResult<&'a str>
 }
 
 
-fn parse_userinfo<'a>(input: &'a str, parser: &UrlParser)
+fn parse_userinfo<'a>(input: &'a str, parser: &UrlParser, buffers: &mut Buffers)
                       -> ParseResult<(String, Option<String>, &'a str)> {
     let mut last_at = None;
     for (i, c) in input.char_indices() {
@@ -333,16 +544,24 @@ fn parse_userinfo<'a>(input: &'a str, parser: &UrlParser)
         None => return Ok((String::new(), None, input)),
     };
 
-    let mut username = String::new();
+    let mut username = buffers.string();
     let mut password = None;
-    for (i, c, next_i) in input.char_ranges() {
+    let canonical = canonical_stops(USERINFO_ENCODE_SET, b":");
+    let mut iter = input.char_ranges();
+    while let Some((i, c, next_i)) = iter.next() {
         match c {
             ':' => {
-                password = Some(try!(parse_password(&input[i + 1..], parser)));
+                password = Some(try!(parse_password(&input[i + 1..], parser, buffers)));
                 break
             },
             '\t' | '\n' | '\r' => try!(parser.parse_error(ParseError::InvalidCharacter)),
             _ => {
+                let run = scan::run_length(&input.as_bytes()[i..], &canonical);
+                if run > 0 {
+                    username.push_str(&input[i..i + run]);
+                    iter.position = i + run;
+                    continue
+                }
                 try!(check_url_code_point(input, i, c, parser));
                 // The spec says to use the default encode set,
                 // but also replaces '@' by '%40' in an earlier step.
@@ -355,12 +574,21 @@ fn parse_userinfo<'a>(input: &'a str, parser: &UrlParser)
 }
 
 
-fn parse_password(input: &str, parser: &UrlParser) -> ParseResult<String> {
-    let mut password = String::new();
-    for (i, c, next_i) in input.char_ranges() {
+fn parse_password(input: &str, parser: &UrlParser, buffers: &mut Buffers)
+                  -> ParseResult<String> {
+    let mut password = buffers.string();
+    let canonical = canonical_stops(USERINFO_ENCODE_SET, b"");
+    let mut iter = input.char_ranges();
+    while let Some((i, c, next_i)) = iter.next() {
         match c {
             '\t' | '\n' | '\r' => try!(parser.parse_error(ParseError::InvalidCharacter)),
             _ => {
+                let run = scan::run_length(&input.as_bytes()[i..], &canonical);
+                if run > 0 {
+                    password.push_str(&input[i..i + run]);
+                    iter.position = i + run;
+                    continue
+                }
                 try!(check_url_code_point(input, i, c, parser));
                 // The spec says to use the default encode set,
                 // but also replaces '@' by '%40' in an earlier step.
@@ -407,6 +635,8 @@ pub fn parse_hostname<'a>(input: &'a str, parser: &UrlParser)
                     ']' => inside_square_brackets = false,
                     _ => (),
                 }
+                try!(check_limit(host_input.len() + c.len_utf8(), parser.limits.max_host_length,
+                                 ParseError::HostTooLong));
                 host_input.push(c)
             }
         }
@@ -457,7 +687,11 @@ fn parse_file_host<'a>(input: &'a str, parser: &UrlParser) -> ParseResult<(Host,
                 break
             },
             '\t' | '\n' | '\r' => try!(parser.parse_error(ParseError::InvalidCharacter)),
-            _ => host_input.push(c)
+            _ => {
+                try!(check_limit(host_input.len() + c.len_utf8(), parser.limits.max_host_length,
+                                 ParseError::HostTooLong));
+                host_input.push(c)
+            }
         }
     }
     let host = if host_input.is_empty() {
@@ -471,6 +705,10 @@ fn parse_file_host<'a>(input: &'a str, parser: &UrlParser) -> ParseResult<(Host,
 
 pub fn parse_standalone_path(input: &str, parser: &UrlParser)
                              -> ParseResult<(Vec<String>, Option<String>, Option<String>)> {
+    try!(check_limit(input.len(), parser.limits.max_length, ParseError::UrlTooLong));
+    if parser.strict_rfc3986 {
+        try!(::rfc3986::validate(input, true));
+    }
     if !input.starts_with("/") {
         if input.starts_with("\\") {
             try!(parser.parse_error(ParseError::InvalidBackslash));
@@ -478,9 +716,10 @@ pub fn parse_standalone_path(input: &str, parser: &UrlParser)
             return Err(ParseError::ExpectedInitialSlash)
         }
     }
+    let mut buffers = Buffers::new();
     let (path, remaining) = try!(parse_path(
-        &[], &input[1..], Context::UrlParser, SchemeType::Relative(0), parser));
-    let (query, fragment) = try!(parse_query_and_fragment(remaining, parser));
+        &[], &input[1..], Context::UrlParser, SchemeType::Relative(0), parser, &mut buffers));
+    let (query, fragment) = try!(parse_query_and_fragment(remaining, parser, &mut buffers));
     Ok((path, query, fragment))
 }
 
@@ -488,6 +727,13 @@ pub fn parse_standalone_path(input: &str, parser: &UrlParser)
 pub fn parse_path_start<'a>(input: &'a str, context: Context, scheme_type: SchemeType,
                             parser: &UrlParser)
                             -> ParseResult<(Vec<String>, &'a str)> {
+    parse_path_start_with_buffers(input, context, scheme_type, parser, &mut Buffers::new())
+}
+
+
+fn parse_path_start_with_buffers<'a>(input: &'a str, context: Context, scheme_type: SchemeType,
+                                     parser: &UrlParser, buffers: &mut Buffers)
+                                     -> ParseResult<(Vec<String>, &'a str)> {
     let mut i = 0;
     // Relative path start state
     if !input.is_empty() {
@@ -500,20 +746,23 @@ pub fn parse_path_start<'a>(input: &'a str, context: Context, scheme_type: Schem
             _ => ()
         }
     }
-    parse_path(&[], &input[i..], context, scheme_type, parser)
+    parse_path(&[], &input[i..], context, scheme_type, parser, buffers)
 }
 
 
 fn parse_path<'a>(base_path: &[String], input: &'a str, context: Context,
-                  scheme_type: SchemeType, parser: &UrlParser)
+                  scheme_type: SchemeType, parser: &UrlParser, buffers: &mut Buffers)
                   -> ParseResult<(Vec<String>, &'a str)> {
     // Relative path state
-    let mut path = base_path.to_vec();
+    let mut path = buffers.path();
+    path.push_all(base_path);
+    let canonical = canonical_stops(DEFAULT_ENCODE_SET, b"/?");
     let mut iter = input.char_ranges();
     let mut end;
     loop {
-        let mut path_part = String::new();
+        let mut path_part = buffers.string();
         let mut ends_with_slash = false;
+        let segment_start = iter.position;
         end = input.len();
         while let Some((i, c, next_i)) = iter.next() {
             match c {
@@ -534,6 +783,17 @@ fn parse_path<'a>(base_path: &[String], input: &'a str, context: Context,
                 },
                 '\t' | '\n' | '\r' => try!(parser.parse_error(ParseError::InvalidCharacter)),
                 _ => {
+                    let run = scan::run_length(&input.as_bytes()[i..], &canonical);
+                    if run > 0 {
+                        try!(check_limit(i + run - segment_start,
+                                         parser.limits.max_path_segment_length,
+                                         ParseError::PathSegmentTooLong));
+                        path_part.push_str(&input[i..i + run]);
+                        iter.position = i + run;
+                        continue
+                    }
+                    try!(check_limit(next_i - segment_start, parser.limits.max_path_segment_length,
+                                     ParseError::PathSegmentTooLong));
                     try!(check_url_code_point(input, i, c, parser));
                     utf8_percent_encode_to(&input[i..next_i],
                                         DEFAULT_ENCODE_SET, &mut path_part);
@@ -545,11 +805,15 @@ fn parse_path<'a>(base_path: &[String], input: &'a str, context: Context,
             "%2e%2e" | "%2E%2e" | "%2e%2E" | "%2E%2E" => {
                 path.pop();
                 if !ends_with_slash {
+                    try!(check_limit(path.len() + 1, parser.limits.max_path_segments,
+                                     ParseError::TooManyPathSegments));
                     path.push(String::new());
                 }
             },
             "." | "%2e" | "%2E" => {
                 if !ends_with_slash {
+                    try!(check_limit(path.len() + 1, parser.limits.max_path_segments,
+                                     ParseError::TooManyPathSegments));
                     path.push(String::new());
                 }
             },
@@ -564,6 +828,8 @@ fn parse_path<'a>(base_path: &[String], input: &'a str, context: Context,
                         path_part.as_mut_vec()[1] = b':'
                     }
                 }
+                try!(check_limit(path.len() + 1, parser.limits.max_path_segments,
+                                 ParseError::TooManyPathSegments));
                 path.push(path_part)
             }
         }
@@ -575,11 +841,13 @@ fn parse_path<'a>(base_path: &[String], input: &'a str, context: Context,
 }
 
 
-fn parse_scheme_data<'a>(input: &'a str, parser: &UrlParser)
+fn parse_scheme_data<'a>(input: &'a str, parser: &UrlParser, buffers: &mut Buffers)
                          -> ParseResult<(String, &'a str)> {
-    let mut scheme_data = String::new();
+    let mut scheme_data = buffers.string();
+    let canonical = canonical_stops(SIMPLE_ENCODE_SET, b"?");
     let mut end = input.len();
-    for (i, c, next_i) in input.char_ranges() {
+    let mut iter = input.char_ranges();
+    while let Some((i, c, next_i)) = iter.next() {
         match c {
             '?' | '#' => {
                 end = i;
@@ -587,6 +855,12 @@ fn parse_scheme_data<'a>(input: &'a str, parser: &UrlParser)
             },
             '\t' | '\n' | '\r' => try!(parser.parse_error(ParseError::InvalidCharacter)),
             _ => {
+                let run = scan::run_length(&input.as_bytes()[i..], &canonical);
+                if run > 0 {
+                    scheme_data.push_str(&input[i..i + run]);
+                    iter.position = i + run;
+                    continue
+                }
                 try!(check_url_code_point(input, i, c, parser));
                 utf8_percent_encode_to(&input[i..next_i],
                                     SIMPLE_ENCODE_SET, &mut scheme_data);
@@ -597,18 +871,19 @@ fn parse_scheme_data<'a>(input: &'a str, parser: &UrlParser)
 }
 
 
-fn parse_query_and_fragment(input: &str, parser: &UrlParser)
+fn parse_query_and_fragment(input: &str, parser: &UrlParser, buffers: &mut Buffers)
                             -> ParseResult<(Option<String>, Option<String>)> {
     if input.is_empty() {
         return Ok((None, None))
     }
     match input.char_at(0) {
-        '#' => Ok((None, Some(try!(parse_fragment(&input[1..], parser))))),
+        '#' => Ok((None, Some(try!(parse_fragment_with_buffers(&input[1..], parser, buffers))))),
         '?' => {
-            let (query, remaining) = try!(parse_query(
-                &input[1..], Context::UrlParser, parser));
+            let (query, remaining) = try!(parse_query_with_buffers(
+                &input[1..], Context::UrlParser, parser, buffers));
             let fragment = match remaining {
-                Some(remaining) => Some(try!(parse_fragment(remaining, parser))),
+                Some(remaining) => Some(try!(parse_fragment_with_buffers(remaining, parser,
+                                                                         buffers))),
                 None => None
             };
             Ok((Some(query), fragment))
@@ -621,9 +896,26 @@ fn parse_query_and_fragment(input: &str, parser: &UrlParser)
 
 pub fn parse_query<'a>(input: &'a str, context: Context, parser: &UrlParser)
                    -> ParseResult<(String, Option<&'a str>)> {
-    let mut query = String::new();
+    parse_query_with_buffers(input, context, parser, &mut Buffers::new())
+}
+
+
+fn parse_query_with_buffers<'a>(input: &'a str, context: Context, parser: &UrlParser,
+                                buffers: &mut Buffers)
+                                -> ParseResult<(String, Option<&'a str>)> {
+    let query_length = match context {
+        Context::UrlParser => input.find('#').unwrap_or(input.len()),
+        Context::Setter => input.len(),
+    };
+    try!(check_limit(query_length, parser.limits.max_query_length, ParseError::QueryTooLong));
+    // With UTF-8, encode as we go. Otherwise, collect the characters
+    // to encode them all at once with the encoding override.
+    let is_utf8 = parser.query_encoding_override.is_utf8();
+    let mut query = buffers.string();
+    let canonical = canonical_stops(QUERY_ENCODE_SET, b"");
     let mut remaining = None;
-    for (i, c) in input.char_indices() {
+    let mut iter = input.char_ranges();
+    while let Some((i, c, next_i)) = iter.next() {
         match c {
             '#' if context == Context::UrlParser => {
                 remaining = Some(&input[i + 1..]);
@@ -631,23 +923,53 @@ pub fn parse_query<'a>(input: &'a str, context: Context, parser: &UrlParser)
             },
             '\t' | '\n' | '\r' => try!(parser.parse_error(ParseError::InvalidCharacter)),
             _ => {
+                if is_utf8 {
+                    let run = scan::run_length(&input.as_bytes()[i..], &canonical);
+                    if run > 0 {
+                        query.push_str(&input[i..i + run]);
+                        iter.position = i + run;
+                        continue
+                    }
+                }
                 try!(check_url_code_point(input, i, c, parser));
-                query.push(c);
+                if is_utf8 {
+                    utf8_percent_encode_to(&input[i..next_i], QUERY_ENCODE_SET, &mut query);
+                } else {
+                    query.push(c);
+                }
             }
         }
     }
 
-    let query_bytes = parser.query_encoding_override.encode(query.as_slice());
-    Ok((percent_encode(query_bytes.as_slice(), QUERY_ENCODE_SET), remaining))
+    if is_utf8 {
+        Ok((query, remaining))
+    } else {
+        let query_bytes = parser.query_encoding_override.encode(query.as_slice());
+        Ok((percent_encode(query_bytes.as_slice(), QUERY_ENCODE_SET), remaining))
+    }
 }
 
 
 pub fn parse_fragment<'a>(input: &'a str, parser: &UrlParser) -> ParseResult<String> {
-    let mut fragment = String::new();
-    for (i, c, next_i) in input.char_ranges() {
+    parse_fragment_with_buffers(input, parser, &mut Buffers::new())
+}
+
+
+fn parse_fragment_with_buffers(input: &str, parser: &UrlParser, buffers: &mut Buffers)
+                               -> ParseResult<String> {
+    let mut fragment = buffers.string();
+    let canonical = canonical_stops(SIMPLE_ENCODE_SET, b"");
+    let mut iter = input.char_ranges();
+    while let Some((i, c, next_i)) = iter.next() {
         match c {
             '\t' | '\n' | '\r' => try!(parser.parse_error(ParseError::InvalidCharacter)),
             _ => {
+                let run = scan::run_length(&input.as_bytes()[i..], &canonical);
+                if run > 0 {
+                    fragment.push_str(&input[i..i + run]);
+                    iter.position = i + run;
+                    continue
+                }
                 try!(check_url_code_point(input, i, c, parser));
                 utf8_percent_encode_to(&input[i..next_i],
                                     SIMPLE_ENCODE_SET, &mut fragment);
@@ -658,6 +980,15 @@ pub fn parse_fragment<'a>(input: &'a str, parser: &UrlParser) -> ParseResult<Str
 }
 
 
+/// Return `Err(error)` if `length` is more than `limit`.
+#[inline]
+pub fn check_limit(length: usize, limit: Option<usize>, error: ParseError) -> ParseResult<()> {
+    match limit {
+        Some(limit) if length > limit => Err(error),
+        _ => Ok(()),
+    }
+}
+
 #[inline]
 pub fn starts_with_ascii_alpha(string: &str) -> bool {
     matches!(string.as_bytes()[0], b'a'...b'z' | b'A'...b'Z')
@@ -694,6 +1025,17 @@ fn is_url_code_point(c: char) -> bool {
         '\u{F0000}'...'\u{FFFFD}' | '\u{100000}'...'\u{10FFFD}')
 }
 
+/// Printable ASCII bytes that are not URL code points, and `%`
+/// which needs to be followed by two hex digits.
+static NON_CANONICAL_PRINTABLE: &'static [u8] = b" \"#%<>[\\]^`{|}";
+
+/// Return the set of bytes that can not be copied as-is: all but the URL code points
+/// in the ASCII range, plus `%`, `stops`, and the bytes in `encode_set`.
+#[inline]
+fn canonical_stops(encode_set: EncodeSet, stops: &[u8]) -> ByteSet {
+    ByteSet::non_printable_and(&[NON_CANONICAL_PRINTABLE, encode_set.printable_ascii(), stops])
+}
+
 // Non URL code points:
 // U+0000 to U+0020 (space)
 // " # % < > [ \ ] ^ ` { | }
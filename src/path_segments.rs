@@ -0,0 +1,182 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Iteration over and editing of the path segments of an URL in a *relative scheme*,
+//! with percent-encoding handled automatically.
+
+use std::slice;
+use parser::{ParseResult, ParseError};
+use percent_encoding::{utf8_percent_encode_to, lossy_utf8_percent_decode,
+                       PATH_SEGMENT_ENCODE_SET};
+
+
+/// An iterator of the percent-encoded path segments of an URL,
+/// returned by `Url::path_segments`.
+pub struct PathSegments<'a> {
+    iter: slice::Iter<'a, String>,
+}
+
+
+impl<'a> Iterator for PathSegments<'a> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a str> {
+        self.iter.next().map(|segment| segment.as_slice())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+
+impl<'a> DoubleEndedIterator for PathSegments<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a str> {
+        self.iter.next_back().map(|segment| segment.as_slice())
+    }
+}
+
+
+/// An iterator of the percent-decoded path segments of an URL,
+/// returned by `Url::lossy_percent_decode_path_segments`.
+///
+/// This is “lossy”: invalid UTF-8 percent-encoded byte sequences
+/// are replaced by � U+FFFD, the replacement character.
+pub struct LossyDecodedPathSegments<'a> {
+    iter: slice::Iter<'a, String>,
+}
+
+
+impl<'a> Iterator for LossyDecodedPathSegments<'a> {
+    type Item = String;
+
+    #[inline]
+    fn next(&mut self) -> Option<String> {
+        self.iter.next().map(|segment| lossy_utf8_percent_decode(segment.as_bytes()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+
+impl<'a> DoubleEndedIterator for LossyDecodedPathSegments<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<String> {
+        self.iter.next_back().map(|segment| lossy_utf8_percent_decode(segment.as_bytes()))
+    }
+}
+
+
+/// A guard that edits the path segments of an URL, returned by `Url::path_segments_mut`.
+///
+/// Segments are given unencoded, and are percent-encoded as they are added,
+/// including `/` and `%`.
+/// A trailing slash is represented by an empty last segment.
+///
+/// ```
+/// use url::Url;
+///
+/// let mut url = Url::parse("https://api.example.com/v1/").unwrap();
+/// url.path_segments_mut().unwrap().pop_if_empty().push("users").unwrap().push("a/b c").unwrap();
+/// assert_eq!(url.serialize(), "https://api.example.com/v1/users/a%2Fb%20c".to_string());
+/// ```
+pub struct PathSegmentsMut<'a> {
+    path: &'a mut Vec<String>,
+}
+
+
+impl<'a> PathSegmentsMut<'a> {
+    /// Percent-encode `segment` and append it.
+    ///
+    /// `.` and `..` are a `DotPathSegment` error, since they would be removed
+    /// or go up a level when the URL is parsed again.
+    /// Percent-encoding them does not help: `%2E%2E` is also parsed as `..`.
+    pub fn push(&mut self, segment: &str) -> ParseResult<&mut PathSegmentsMut<'a>> {
+        self.path.push(try!(encode_segment(segment)));
+        Ok(self)
+    }
+
+    /// Percent-encode and append each segment, like `push`.
+    ///
+    /// If any segment is `.` or `..`, none is appended.
+    pub fn extend<'b, I>(&mut self, segments: I) -> ParseResult<&mut PathSegmentsMut<'a>>
+                         where I: Iterator<Item = &'b str> {
+        let mut encoded = Vec::new();
+        for segment in segments {
+            encoded.push(try!(encode_segment(segment)));
+        }
+        self.path.extend(encoded.into_iter());
+        Ok(self)
+    }
+
+    /// Remove the last segment, if any.
+    #[inline]
+    pub fn pop(&mut self) -> &mut PathSegmentsMut<'a> {
+        self.path.pop();
+        self
+    }
+
+    /// Remove the last segment if it is empty, as with a trailing slash.
+    #[inline]
+    pub fn pop_if_empty(&mut self) -> &mut PathSegmentsMut<'a> {
+        if self.path.last().map_or(false, |segment| segment.is_empty()) {
+            self.path.pop();
+        }
+        self
+    }
+
+    /// Add or remove a trailing slash.
+    pub fn trailing_slash(&mut self, value: bool) -> &mut PathSegmentsMut<'a> {
+        let has_trailing_slash = self.path.last().map_or(false, |segment| segment.is_empty());
+        if value && !has_trailing_slash {
+            self.path.push(String::new());
+        } else if !value && has_trailing_slash {
+            self.path.pop();
+        }
+        self
+    }
+
+    /// Remove all segments, leaving the path as `/`.
+    #[inline]
+    pub fn clear(&mut self) -> &mut PathSegmentsMut<'a> {
+        self.path.truncate(0);
+        self
+    }
+}
+
+
+fn encode_segment(segment: &str) -> ParseResult<String> {
+    if segment == "." || segment == ".." {
+        return Err(ParseError::DotPathSegment)
+    }
+    let mut encoded = String::new();
+    utf8_percent_encode_to(segment, PATH_SEGMENT_ENCODE_SET, &mut encoded);
+    Ok(encoded)
+}
+
+
+#[inline]
+pub fn path_segments(path: &[String]) -> PathSegments {
+    PathSegments { iter: path.iter() }
+}
+
+#[inline]
+pub fn lossy_decoded_path_segments(path: &[String]) -> LossyDecodedPathSegments {
+    LossyDecodedPathSegments { iter: path.iter() }
+}
+
+#[inline]
+pub fn path_segments_mut(path: &mut Vec<String>) -> PathSegmentsMut {
+    PathSegmentsMut { path: path }
+}
@@ -7,6 +7,9 @@
 // except according to those terms.
 
 
+use std::str;
+use scan;
+
 #[path = "encode_sets.rs"]
 mod encode_sets;
 
@@ -30,29 +33,100 @@ mod encode_sets;
 #[derive(Copy)]
 pub struct EncodeSet {
     map: &'static [&'static str; 256],
+    printable: &'static [u8],
+    stops: &'static [u64; 4],
+}
+
+impl EncodeSet {
+    /// Return whether this encode set percent-encodes `byte`.
+    #[inline]
+    pub fn contains(&self, byte: u8) -> bool {
+        self.map[byte as usize].len() != 1
+    }
+
+    /// Return the percent-encoded form of `byte`, or `byte` itself as a string
+    /// if this encode set does not percent-encode it.
+    #[inline]
+    pub fn encode_byte(&self, byte: u8) -> &'static str {
+        self.map[byte as usize]
+    }
+
+    /// Return the bytes in the printable ASCII range, 0x20 space to 0x7E `~`,
+    /// that this encode set percent-encodes.
+    ///
+    /// Every encode set also percent-encodes all the other bytes:
+    /// control characters, 0x7F and non-ASCII bytes.
+    #[inline]
+    pub fn printable_ascii(&self) -> &'static [u8] {
+        self.printable
+    }
 }
 
 /// This encode set is used for fragment identifier and non-relative scheme data.
-pub static SIMPLE_ENCODE_SET: EncodeSet = EncodeSet { map: &encode_sets::SIMPLE };
+pub static SIMPLE_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::SIMPLE,
+    printable: encode_sets::SIMPLE_PRINTABLE,
+    stops: &encode_sets::SIMPLE_STOPS,
+};
 
 /// This encode set is used in the URL parser for query strings.
-pub static QUERY_ENCODE_SET: EncodeSet = EncodeSet { map: &encode_sets::QUERY };
+pub static QUERY_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::QUERY,
+    printable: encode_sets::QUERY_PRINTABLE,
+    stops: &encode_sets::QUERY_STOPS,
+};
 
 /// This encode set is used for path components.
-pub static DEFAULT_ENCODE_SET: EncodeSet = EncodeSet { map: &encode_sets::DEFAULT };
+pub static DEFAULT_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::DEFAULT,
+    printable: encode_sets::DEFAULT_PRINTABLE,
+    stops: &encode_sets::DEFAULT_STOPS,
+};
+
+/// This encode set should be used when setting a single path segment of a parsed URL.
+/// It is the default encode set plus `/`, `\` and `%`,
+/// so that the input is one segment with no percent-encoded sequence.
+pub static PATH_SEGMENT_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::PATH_SEGMENT,
+    printable: encode_sets::PATH_SEGMENT_PRINTABLE,
+    stops: &encode_sets::PATH_SEGMENT_STOPS,
+};
+
+/// This encode set should be used when setting the name or a parameter of a path segment
+/// with matrix parameters.
+/// It is the path segment encode set plus `;` and `=`.
+pub static MATRIX_PARAM_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::MATRIX_PARAM,
+    printable: encode_sets::MATRIX_PARAM_PRINTABLE,
+    stops: &encode_sets::MATRIX_PARAM_STOPS,
+};
 
 /// This encode set is used in the URL parser for usernames and passwords.
-pub static USERINFO_ENCODE_SET: EncodeSet = EncodeSet { map: &encode_sets::USERINFO };
+pub static USERINFO_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::USERINFO,
+    printable: encode_sets::USERINFO_PRINTABLE,
+    stops: &encode_sets::USERINFO_STOPS,
+};
 
 /// This encode set should be used when setting the password field of a parsed URL.
-pub static PASSWORD_ENCODE_SET: EncodeSet = EncodeSet { map: &encode_sets::PASSWORD };
+pub static PASSWORD_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::PASSWORD,
+    printable: encode_sets::PASSWORD_PRINTABLE,
+    stops: &encode_sets::PASSWORD_STOPS,
+};
 
 /// This encode set should be used when setting the username field of a parsed URL.
-pub static USERNAME_ENCODE_SET: EncodeSet = EncodeSet { map: &encode_sets::USERNAME };
+pub static USERNAME_ENCODE_SET: EncodeSet = EncodeSet {
+    map: &encode_sets::USERNAME,
+    printable: encode_sets::USERNAME_PRINTABLE,
+    stops: &encode_sets::USERNAME_STOPS,
+};
 
 /// This encode set is used in `application/x-www-form-urlencoded` serialization.
 pub static FORM_URLENCODED_ENCODE_SET: EncodeSet = EncodeSet {
     map: &encode_sets::FORM_URLENCODED,
+    printable: encode_sets::FORM_URLENCODED_PRINTABLE,
+    stops: &encode_sets::FORM_URLENCODED_STOPS,
 };
 
 
@@ -61,8 +135,18 @@ pub static FORM_URLENCODED_ENCODE_SET: EncodeSet = EncodeSet {
 /// The pushed strings are within the ASCII range.
 #[inline]
 pub fn percent_encode_to(input: &[u8], encode_set: EncodeSet, output: &mut String) {
-    for &byte in input.iter() {
-        output.push_str(encode_set.map[byte as usize])
+    let stops = scan::ByteSet::from_bits(*encode_set.stops);
+    let mut i = 0;
+    while i < input.len() {
+        // Copy the bytes that do not need encoding all at once.
+        let run = scan::run_length(&input[i..], &stops);
+        // Safe because the run is in the ASCII range.
+        output.push_str(unsafe { str::from_utf8_unchecked(&input[i..i + run]) });
+        i += run;
+        if i < input.len() {
+            output.push_str(encode_set.map[input[i] as usize]);
+            i += 1;
+        }
     }
 }
 
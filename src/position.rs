@@ -0,0 +1,273 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Positions of the components in the serialization of an URL,
+//! to take substrings such as “everything up to the path” or “just host:port”.
+//!
+//! `Url` keeps its components separately and does not store its serialization,
+//! so it can not be sliced directly: `url[Position::BeforePath..]` is written
+//! `url.to_serialized()[Position::BeforePath..]`.
+//! `to_serialized` allocates the serialization once, after which any number of slices
+//! are free.
+//! To only get byte positions, for example to highlight the host in a displayed URL,
+//! `Url::position` and `Url::range` compute them without allocating.
+//!
+//! ```
+//! use url::{Url, Position};
+//!
+//! let url = Url::parse("https://user@example.com:8443/a/b?q=1#top").unwrap();
+//! assert_eq!(url.range(Position::BeforeHost, Position::AfterHost), 13..24);
+//!
+//! let serialized = url.to_serialized();
+//! assert_eq!(&serialized[..Position::BeforePath], "https://user@example.com:8443");
+//! assert_eq!(&serialized[Position::BeforeHost..Position::AfterPort], "example.com:8443");
+//! assert_eq!(&serialized[Position::BeforePath..Position::AfterQuery], "/a/b?q=1");
+//! ```
+
+use std::fmt::{self, Formatter, Write};
+use std::ops::{Index, Range, RangeFrom, RangeTo, RangeFull};
+use super::{Url, SchemeData};
+use format::PathFormatter;
+
+
+/// A position in the serialization of an URL, before or after one of its components.
+///
+/// Delimiters are not part of the components:
+/// `AfterScheme` is before the `:` after the scheme, `BeforeQuery` is after the `?`, etc.
+/// `BeforeUsername` is after the `//` of URLs in a *relative scheme*.
+///
+/// When a component is not present, its `Before*` and `After*` positions are the same,
+/// at the position where it would be.
+/// URLs in a *non-relative scheme* have no username, password, host or port,
+/// and their scheme data is in the path position.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Position {
+    BeforeScheme,
+    AfterScheme,
+    BeforeUsername,
+    AfterUsername,
+    BeforePassword,
+    AfterPassword,
+    BeforeHost,
+    AfterHost,
+    BeforePort,
+    AfterPort,
+    BeforePath,
+    AfterPath,
+    BeforeQuery,
+    AfterQuery,
+    BeforeFragment,
+    AfterFragment,
+}
+
+
+const POSITION_COUNT: usize = 16;
+
+
+/// The serialization of an URL together with the byte position of each of its components,
+/// returned by `Url::to_serialized`.
+///
+/// Index it with a range of `Position`s to get a substring.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SerializedUrl {
+    serialization: String,
+    offsets: [usize; POSITION_COUNT],
+}
+
+
+impl SerializedUrl {
+    /// Serialize `url`, recording the position of each component.
+    pub fn new(url: &Url) -> SerializedUrl {
+        let (serialization, offsets) = write_with_offsets(url, String::new());
+        SerializedUrl { serialization: serialization, offsets: offsets }
+    }
+
+    /// Return the serialization.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self.serialization.as_slice()
+    }
+
+    /// Return the serialization, consuming this `SerializedUrl`.
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.serialization
+    }
+
+    /// Return the byte index of `position` in the serialization.
+    #[inline]
+    pub fn position(&self, position: Position) -> usize {
+        self.offsets[position as usize]
+    }
+
+    /// Return the range of byte indices between `start` and `end` in the serialization,
+    /// for example to highlight the host with `range(BeforeHost, AfterHost)`.
+    ///
+    /// If `end` is before `start`, the range is empty.
+    #[inline]
+    pub fn range(&self, start: Position, end: Position) -> Range<usize> {
+        range(&self.offsets, start, end)
+    }
+}
+
+
+impl Index<Range<Position>> for SerializedUrl {
+    type Output = str;
+    #[inline]
+    fn index(&self, range: &Range<Position>) -> &str {
+        &self.serialization[self.range(range.start, range.end)]
+    }
+}
+
+impl Index<RangeFrom<Position>> for SerializedUrl {
+    type Output = str;
+    #[inline]
+    fn index(&self, range: &RangeFrom<Position>) -> &str {
+        &self.serialization[self.position(range.start)..]
+    }
+}
+
+impl Index<RangeTo<Position>> for SerializedUrl {
+    type Output = str;
+    #[inline]
+    fn index(&self, range: &RangeTo<Position>) -> &str {
+        &self.serialization[..self.position(range.end)]
+    }
+}
+
+impl Index<RangeFull> for SerializedUrl {
+    type Output = str;
+    #[inline]
+    fn index(&self, _: &RangeFull) -> &str {
+        self.serialization.as_slice()
+    }
+}
+
+
+impl fmt::Display for SerializedUrl {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str(self.serialization.as_slice())
+    }
+}
+
+
+/// Return the byte position of each component in the serialization of `url`,
+/// without allocating it.
+pub fn offsets(url: &Url) -> [usize; POSITION_COUNT] {
+    write_with_offsets(url, Discard).1
+}
+
+
+/// Return the range between `start` and `end` in `offsets`, empty if `end` is before `start`.
+#[inline]
+pub fn range(offsets: &[usize; POSITION_COUNT], start: Position, end: Position) -> Range<usize> {
+    let start = offsets[start as usize];
+    let end = offsets[end as usize];
+    if end < start { start..start } else { start..end }
+}
+
+
+/// Write the serialization of `url` to `output`, recording the position of each component.
+fn write_with_offsets<W: Write>(url: &Url, output: W) -> (W, [usize; POSITION_COUNT]) {
+    let mut output = CountingWriter { output: output, len: 0 };
+    let mut offsets = [0; POSITION_COUNT];
+    // Writing to a `String` or to `Discard` never fails.
+    macro_rules! push {
+        ($($arg: tt)+) => { write!(output, $($arg)+).unwrap() }
+    }
+    macro_rules! mark {
+        ($($position: ident),+) => {
+            $( offsets[Position::$position as usize] = output.len; )+
+        }
+    }
+
+    mark!(BeforeScheme);
+    push!("{}", url.scheme);
+    mark!(AfterScheme);
+    push!(":");
+    match url.scheme_data {
+        SchemeData::Relative(ref scheme_data) => {
+            push!("//");
+            mark!(BeforeUsername);
+            push!("{}", scheme_data.username);
+            mark!(AfterUsername);
+            if let Some(ref password) = scheme_data.password {
+                push!(":");
+                mark!(BeforePassword);
+                push!("{}", password);
+            } else {
+                mark!(BeforePassword);
+            }
+            mark!(AfterPassword);
+            if !scheme_data.username.is_empty() || scheme_data.password.is_some() {
+                push!("@");
+            }
+            mark!(BeforeHost);
+            push!("{}", scheme_data.host);
+            mark!(AfterHost);
+            if let Some(port) = scheme_data.port {
+                push!(":");
+                mark!(BeforePort);
+                push!("{}", port);
+            } else {
+                mark!(BeforePort);
+            }
+            mark!(AfterPort, BeforePath);
+            push!("{}", PathFormatter { path: scheme_data.path.as_slice() });
+        },
+        SchemeData::NonRelative(ref scheme_data) => {
+            mark!(BeforeUsername, AfterUsername, BeforePassword, AfterPassword,
+                  BeforeHost, AfterHost, BeforePort, AfterPort, BeforePath);
+            push!("{}", scheme_data);
+        },
+    }
+    mark!(AfterPath);
+    if let Some(ref query) = url.query {
+        push!("?");
+        mark!(BeforeQuery);
+        push!("{}", query);
+    } else {
+        mark!(BeforeQuery);
+    }
+    mark!(AfterQuery);
+    if let Some(ref fragment) = url.fragment {
+        push!("#");
+        mark!(BeforeFragment);
+        push!("{}", fragment);
+    } else {
+        mark!(BeforeFragment);
+    }
+    mark!(AfterFragment);
+    (output.output, offsets)
+}
+
+
+/// Count the bytes written to the inner writer.
+struct CountingWriter<W> {
+    output: W,
+    len: usize,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.len += s.len();
+        self.output.write_str(s)
+    }
+}
+
+
+/// A writer that drops everything, to only count bytes.
+struct Discard;
+
+impl Write for Discard {
+    #[inline]
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! In-place editing of an URL’s query string as (name, value) pairs,
+//! similar to the [`URLSearchParams`](http://url.spec.whatwg.org/#urlsearchparams)
+//! interface of the URL Standard.
+
+use std::iter::order;
+use std::slice;
+use super::Url;
+use form_urlencoded;
+
+
+/// A guard that edits the query string of an URL as (name, value) pairs,
+/// returned by `Url::query_pairs_mut`.
+///
+/// Pairs keep their order, and names can be repeated.
+/// When the guard is dropped, if any pair was changed,
+/// the query string is replaced by the `application/x-www-form-urlencoded` serialization
+/// of the pairs, or removed if there is no pair left.
+///
+/// ```
+/// use url::Url;
+///
+/// let mut url = Url::parse("http://example.com/?a=1&b=2&a=3").unwrap();
+/// {
+///     let mut pairs = url.query_pairs_mut();
+///     pairs.set("a", "4").append("c", "é");
+///     assert_eq!(pairs.get("b"), Some("2"));
+/// }
+/// assert_eq!(url.serialize(), "http://example.com/?a=4&b=2&c=%C3%A9".to_string());
+/// ```
+pub struct QueryPairsMut<'a> {
+    url: &'a mut Url,
+    pairs: Vec<(String, String)>,
+    modified: bool,
+}
+
+
+impl<'a> QueryPairsMut<'a> {
+    #[inline]
+    fn new(url: &'a mut Url) -> QueryPairsMut<'a> {
+        let pairs = url.query_pairs().unwrap_or_else(Vec::new);
+        QueryPairsMut { url: url, pairs: pairs, modified: false }
+    }
+
+    /// Add a pair at the end, even if there are already pairs with the same name.
+    pub fn append(&mut self, name: &str, value: &str) -> &mut QueryPairsMut<'a> {
+        self.pairs.push((name.to_string(), value.to_string()));
+        self.modified = true;
+        self
+    }
+
+    /// Set the value of the first pair named `name`, and remove the other pairs with that name.
+    /// If there is no such pair, add one at the end.
+    pub fn set(&mut self, name: &str, value: &str) -> &mut QueryPairsMut<'a> {
+        let mut found = false;
+        let mut i = 0;
+        while i < self.pairs.len() {
+            if self.pairs[i].0 == name {
+                if found {
+                    self.pairs.remove(i);
+                    continue
+                }
+                self.pairs[i].1 = value.to_string();
+                found = true;
+            }
+            i += 1;
+        }
+        if !found {
+            self.pairs.push((name.to_string(), value.to_string()));
+        }
+        self.modified = true;
+        self
+    }
+
+    /// Return the value of the first pair named `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs.iter().find(|&&(ref n, _)| n.as_slice() == name).map(|&(_, ref v)| v.as_slice())
+    }
+
+    /// Return the values of all pairs named `name`, in order.
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.pairs.iter().filter(|&&(ref n, _)| n.as_slice() == name)
+                  .map(|&(_, ref v)| v.as_slice()).collect()
+    }
+
+    /// Remove all pairs named `name`.
+    pub fn delete(&mut self, name: &str) -> &mut QueryPairsMut<'a> {
+        let length = self.pairs.len();
+        self.pairs.retain(|&(ref n, _)| n.as_slice() != name);
+        if self.pairs.len() != length {
+            self.modified = true;
+        }
+        self
+    }
+
+    /// Return whether there is a pair named `name`.
+    #[inline]
+    pub fn has(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Sort the pairs by name, comparing UTF-16 code units like `URLSearchParams.sort()`.
+    ///
+    /// The sort is stable: pairs with the same name keep their relative order.
+    pub fn sort(&mut self) -> &mut QueryPairsMut<'a> {
+        self.pairs.sort_by(|&(ref a, _), &(ref b, _)| {
+            order::cmp(a.utf16_units(), b.utf16_units())
+        });
+        self.modified = true;
+        self
+    }
+
+    /// Remove all pairs.
+    pub fn clear(&mut self) -> &mut QueryPairsMut<'a> {
+        self.pairs.truncate(0);
+        self.modified = true;
+        self
+    }
+
+    /// Return the number of pairs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Return whether there is no pair.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Return an iterator of the (name, value) pairs, in order.
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<(String, String)> {
+        self.pairs.iter()
+    }
+}
+
+
+#[unsafe_destructor]
+impl<'a> Drop for QueryPairsMut<'a> {
+    fn drop(&mut self) {
+        if self.modified {
+            self.url.query = if self.pairs.is_empty() {
+                None
+            } else {
+                Some(form_urlencoded::serialize_owned(self.pairs.as_slice()))
+            };
+        }
+    }
+}
+
+
+#[inline]
+pub fn query_pairs_mut(url: &mut Url) -> QueryPairsMut {
+    QueryPairsMut::new(url)
+}
@@ -0,0 +1,232 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Validation of URI references against the [RFC 3986](http://tools.ietf.org/html/rfc3986)
+//! ABNF, used by the strict parsing mode of `UrlParser`.
+
+use parser::{ParseResult, ParseError};
+use percent_encoding::from_hex;
+
+
+/// Check that `input` matches the `URI` rule of RFC 3986,
+/// or the `URI-reference` rule if `allow_relative` is true.
+pub fn validate(input: &str, allow_relative: bool) -> ParseResult<()> {
+    let input = input.as_bytes();
+    let (input, fragment) = split_at_first(input, b'#');
+    let (input, query) = split_at_first(input, b'?');
+    match scheme_end(input) {
+        Some(end) => try!(validate_hier_part(&input[end + 1..])),
+        None => {
+            if !allow_relative {
+                let first_segment_end = input.position_elem(&b'/').unwrap_or(input.len());
+                return Err(if input[..first_segment_end].contains(&b':') {
+                    ParseError::InvalidScheme
+                } else {
+                    ParseError::RelativeUrlWithoutBase
+                })
+            }
+            try!(validate_relative_part(input))
+        }
+    }
+    if let Some(query) = query {
+        try!(validate_component(query, is_query_byte, ParseError::InvalidQueryCharacter));
+    }
+    if let Some(fragment) = fragment {
+        try!(validate_component(fragment, is_query_byte, ParseError::InvalidFragmentCharacter));
+    }
+    Ok(())
+}
+
+
+fn split_at_first(input: &[u8], delimiter: u8) -> (&[u8], Option<&[u8]>) {
+    match input.position_elem(&delimiter) {
+        Some(position) => (&input[..position], Some(&input[position + 1..])),
+        None => (input, None),
+    }
+}
+
+
+/// scheme = ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+///
+/// Return the position of the `:` delimiter after the scheme, if any.
+fn scheme_end(input: &[u8]) -> Option<usize> {
+    if input.is_empty() || !is_alpha(input[0]) {
+        return None
+    }
+    for (i, &byte) in input.iter().enumerate() {
+        match byte {
+            b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'+' | b'-' | b'.' => (),
+            b':' => return Some(i),
+            _ => return None,
+        }
+    }
+    None
+}
+
+
+/// hier-part = "//" authority path-abempty / path-absolute / path-rootless / path-empty
+fn validate_hier_part(input: &[u8]) -> ParseResult<()> {
+    if input.starts_with(b"//") {
+        validate_authority_and_path(&input[2..])
+    } else {
+        validate_component(input, is_path_byte, ParseError::InvalidPathCharacter)
+    }
+}
+
+
+/// relative-part = "//" authority path-abempty / path-absolute / path-noscheme / path-empty
+fn validate_relative_part(input: &[u8]) -> ParseResult<()> {
+    if input.starts_with(b"//") {
+        validate_authority_and_path(&input[2..])
+    } else {
+        // In path-noscheme, the first segment can not contain a colon
+        // or it would be ambiguous with a scheme.
+        let first_segment_end = input.position_elem(&b'/').unwrap_or(input.len());
+        if input[..first_segment_end].contains(&b':') {
+            return Err(ParseError::InvalidScheme)
+        }
+        validate_component(input, is_path_byte, ParseError::InvalidPathCharacter)
+    }
+}
+
+
+fn validate_authority_and_path(input: &[u8]) -> ParseResult<()> {
+    let authority_end = input.position_elem(&b'/').unwrap_or(input.len());
+    try!(validate_authority(&input[..authority_end]));
+    validate_component(&input[authority_end..], is_path_byte, ParseError::InvalidPathCharacter)
+}
+
+
+/// authority = [ userinfo "@" ] host [ ":" port ]
+fn validate_authority(input: &[u8]) -> ParseResult<()> {
+    let input = match input.position_elem(&b'@') {
+        Some(at) => {
+            try!(validate_component(&input[..at], is_userinfo_byte,
+                                    ParseError::InvalidUserinfoCharacter));
+            &input[at + 1..]
+        },
+        None => input,
+    };
+    let port = if !input.is_empty() && input[0] == b'[' {
+        // IP-literal = "[" ( IPv6address / IPvFuture  ) "]"
+        let end = match input.position_elem(&b']') {
+            Some(end) => end,
+            None => return Err(ParseError::InvalidIpv6Address),
+        };
+        try!(validate_ip_literal(&input[1..end]));
+        let remaining = &input[end + 1..];
+        if remaining.is_empty() {
+            None
+        } else if remaining[0] == b':' {
+            Some(&remaining[1..])
+        } else {
+            return Err(ParseError::InvalidDomainCharacter)
+        }
+    } else {
+        // reg-name = *( unreserved / pct-encoded / sub-delims )
+        // IPv4address is a subset of reg-name.
+        let (host, port) = split_at_first(input, b':');
+        try!(validate_component(host, is_reg_name_byte, ParseError::InvalidDomainCharacter));
+        port
+    };
+    match port {
+        Some(port) if !port.iter().all(|&b| is_digit(b)) => Err(ParseError::InvalidPort),
+        _ => Ok(())
+    }
+}
+
+
+fn validate_ip_literal(input: &[u8]) -> ParseResult<()> {
+    if !input.is_empty() && matches!(input[0], b'v' | b'V') {
+        // IPvFuture = "v" 1*HEXDIG "." 1*( unreserved / sub-delims / ":" )
+        let dot = match input.position_elem(&b'.') {
+            Some(dot) if dot > 1 && dot + 1 < input.len() => dot,
+            _ => return Err(ParseError::InvalidIpv6Address),
+        };
+        if !input[1..dot].iter().all(|&b| from_hex(b).is_some())
+                || !input[dot + 1..].iter().all(|&b| is_userinfo_byte(b)) {
+            return Err(ParseError::InvalidIpv6Address)
+        }
+        // Well-formed, but `Host` has no way to represent it.
+        Err(ParseError::UnsupportedIpFuture)
+    } else if input.iter().all(|&b| b == b':' || b == b'.' || from_hex(b).is_some()) {
+        // The address itself is checked by `Ipv6Address::parse`.
+        Ok(())
+    } else {
+        Err(ParseError::InvalidIpv6Address)
+    }
+}
+
+
+/// Check that every byte of `input` is either allowed by `is_allowed`
+/// or part of a `pct-encoded` sequence.
+fn validate_component(input: &[u8], is_allowed: fn(u8) -> bool, error: ParseError)
+                      -> ParseResult<()> {
+    let mut i = 0;
+    while i < input.len() {
+        let byte = input[i];
+        if byte == b'%' {
+            if i + 2 >= input.len() || from_hex(input[i + 1]).is_none()
+                    || from_hex(input[i + 2]).is_none() {
+                return Err(ParseError::InvalidPercentEncoded)
+            }
+            i += 3;
+        } else if is_allowed(byte) {
+            i += 1;
+        } else {
+            return Err(error)
+        }
+    }
+    Ok(())
+}
+
+
+#[inline]
+fn is_alpha(byte: u8) -> bool {
+    matches!(byte, b'a'...b'z' | b'A'...b'Z')
+}
+
+#[inline]
+fn is_digit(byte: u8) -> bool {
+    matches!(byte, b'0'...b'9')
+}
+
+/// unreserved = ALPHA / DIGIT / "-" / "." / "_" / "~"
+#[inline]
+fn is_unreserved(byte: u8) -> bool {
+    is_alpha(byte) || is_digit(byte) || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// sub-delims = "!" / "$" / "&" / "'" / "(" / ")" / "*" / "+" / "," / ";" / "="
+#[inline]
+fn is_sub_delim(byte: u8) -> bool {
+    matches!(byte, b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=')
+}
+
+fn is_reg_name_byte(byte: u8) -> bool {
+    is_unreserved(byte) || is_sub_delim(byte)
+}
+
+fn is_userinfo_byte(byte: u8) -> bool {
+    is_reg_name_byte(byte) || byte == b':'
+}
+
+/// pchar = unreserved / pct-encoded / sub-delims / ":" / "@"
+#[inline]
+fn is_pchar(byte: u8) -> bool {
+    is_userinfo_byte(byte) || byte == b'@'
+}
+
+fn is_path_byte(byte: u8) -> bool {
+    is_pchar(byte) || byte == b'/'
+}
+
+/// query = fragment = *( pchar / "/" / "?" )
+fn is_query_byte(byte: u8) -> bool {
+    is_path_byte(byte) || byte == b'?'
+}
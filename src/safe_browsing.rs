@@ -0,0 +1,265 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! URL [canonicalization and suffix/prefix expressions](
+//! https://developers.google.com/safe-browsing/v4/urls-hashing)
+//! for Google Safe Browsing lookups.
+//!
+//! Safe Browsing canonicalization is deliberately different from URL parsing:
+//! it accepts input that the URL parser rejects, repeatedly percent-decodes,
+//! and drops the username, password, port and fragment.
+//! Lookups then hash each of the `lookup_expressions` with SHA-256
+//! and compare the hashes with the hash prefixes of the lists.
+//!
+//! ```
+//! use url::safe_browsing;
+//!
+//! let url = safe_browsing::canonicalize("http://A.b.c:8080/1/%2532.html?param=1#x").unwrap();
+//! assert_eq!(url.serialize(), "http://a.b.c/1/2.html?param=1".to_string());
+//! assert_eq!(url.lookup_expressions(), vec![
+//!     "a.b.c/1/2.html?param=1".to_string(), "a.b.c/1/2.html".to_string(),
+//!     "a.b.c/".to_string(), "a.b.c/1/".to_string(),
+//!     "b.c/1/2.html?param=1".to_string(), "b.c/1/2.html".to_string(),
+//!     "b.c/".to_string(), "b.c/1/".to_string(),
+//! ]);
+//! ```
+
+use std::ascii::AsciiExt;
+use std::cmp;
+use std::fmt::{self, Formatter};
+use host::parse_ipv4;
+use percent_encoding::percent_decode;
+
+
+/// An URL after Safe Browsing canonicalization.
+///
+/// Each component is percent-encoded as required for hashing,
+/// and is within the ASCII range.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct CanonicalUrl {
+    /// The scheme, in lower case. `http` if the input did not have one.
+    pub scheme: String,
+
+    /// The host name, in lower case without leading, trailing or repeated dots,
+    /// or an IPv4 address in dotted decimal notation.
+    pub host: String,
+
+    /// The path, starting with `/`, without `.` or `..` segments or repeated slashes.
+    pub path: String,
+
+    /// The query string, if the input had a `?` delimiter.
+    pub query: Option<String>,
+}
+
+
+/// Canonicalize `input` as specified for Safe Browsing.
+///
+/// Return `None` if the host is empty.
+#[inline]
+pub fn canonicalize(input: &str) -> Option<CanonicalUrl> {
+    canonicalize_bytes(input.as_bytes())
+}
+
+
+/// Canonicalize `input` as specified for Safe Browsing.
+///
+/// Unlike `canonicalize`, this accepts bytes that are not UTF-8.
+/// They are percent-encoded in the result.
+pub fn canonicalize_bytes(input: &[u8]) -> Option<CanonicalUrl> {
+    let input: Vec<u8> = trim(input).iter().map(|&b| b)
+                                    .filter(|&b| !matches!(b, b'\t' | b'\r' | b'\n')).collect();
+    let input = match input.position_elem(&b'#') {
+        Some(position) => &input[..position],
+        None => &input[..],
+    };
+
+    let (scheme, rest) = match find(input, b"://") {
+        Some(position) if is_scheme(&input[..position]) => {
+            (input[..position].to_ascii_lowercase(), &input[position + 3..])
+        },
+        _ => (b"http".to_vec(), input),
+    };
+    let authority_end = rest.iter().position(|&b| b == b'/' || b == b'?').unwrap_or(rest.len());
+    let (authority, rest) = (&rest[..authority_end], &rest[authority_end..]);
+    let (path, query) = match rest.position_elem(&b'?') {
+        Some(position) => (&rest[..position], Some(&rest[position + 1..])),
+        None => (rest, None),
+    };
+
+    let host = match authority.iter().rposition(|&b| b == b'@') {
+        Some(position) => &authority[position + 1..],
+        None => authority,
+    };
+    let host = match host.iter().rposition(|&b| b == b':') {
+        Some(position) if host[position + 1..].iter().all(|&b| matches!(b, b'0'...b'9')) => {
+            &host[..position]
+        },
+        _ => host,
+    };
+    let host = canonicalize_host(host);
+    if host.is_empty() {
+        return None
+    }
+
+    Some(CanonicalUrl {
+        scheme: escape(scheme.as_slice()),
+        host: host,
+        path: escape(canonicalize_path(unescape(path).as_slice()).as_slice()),
+        query: query.map(|query| escape(unescape(query).as_slice())),
+    })
+}
+
+
+impl CanonicalUrl {
+    /// Return the canonical URL as a string.
+    #[inline]
+    pub fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    /// Return the host suffix / path prefix expressions to look up, without duplicates.
+    ///
+    /// The hosts are the exact host, then, unless it is an IP address,
+    /// up to four hosts formed by starting with the last five components
+    /// and successively removing the first one, down to two components.
+    /// The paths are the exact path with the query string, the exact path without it,
+    /// then up to four paths formed by starting at `/`
+    /// and successively appending path components with a trailing slash.
+    pub fn lookup_expressions(&self) -> Vec<String> {
+        let mut hosts = vec![self.host.clone()];
+        if parse_ipv4(self.host.as_bytes()).is_none() {
+            let labels: Vec<&str> = self.host.as_slice().split('.').collect();
+            if labels.len() > 2 {
+                for count in (2..cmp::min(5, labels.len() - 1) + 1).rev() {
+                    hosts.push(labels[labels.len() - count..].connect("."));
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        if let Some(ref query) = self.query {
+            paths.push(format!("{}?{}", self.path, query));
+        }
+        paths.push(self.path.clone());
+        let directories: Vec<&str> = self.path[1..].split('/').collect();
+        let mut prefix = "/".to_string();
+        for directory in directories[..directories.len() - 1].iter().take(3) {
+            if !paths.contains(&prefix) {
+                paths.push(prefix.clone());
+            }
+            prefix.push_str(directory);
+            prefix.push('/');
+        }
+        if !paths.contains(&prefix) {
+            paths.push(prefix);
+        }
+
+        let mut expressions = Vec::with_capacity(hosts.len() * paths.len());
+        for host in hosts.iter() {
+            for path in paths.iter() {
+                expressions.push(format!("{}{}", host, path));
+            }
+        }
+        expressions
+    }
+}
+
+
+impl fmt::Display for CanonicalUrl {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        try!(write!(formatter, "{}://{}{}", self.scheme, self.host, self.path));
+        if let Some(ref query) = self.query {
+            try!(write!(formatter, "?{}", query));
+        }
+        Ok(())
+    }
+}
+
+
+fn canonicalize_host(host: &[u8]) -> String {
+    let host = unescape(host).to_ascii_lowercase();
+    let labels: Vec<&[u8]> = host.as_slice().split(|&b| b == b'.')
+                                 .filter(|label| !label.is_empty()).collect();
+    let host = labels.connect(&b'.');
+    match parse_ipv4(host.as_slice()) {
+        Some(address) => format!("{}.{}.{}.{}", address[0], address[1], address[2], address[3]),
+        None => escape(host.as_slice()),
+    }
+}
+
+
+/// Remove `.` and `..` segments and empty segments, keeping a trailing slash.
+fn canonicalize_path(path: &[u8]) -> Vec<u8> {
+    let segments: Vec<&[u8]> = if path.is_empty() {
+        vec![]
+    } else {
+        path[1..].split(|&b| b == b'/').collect()
+    };
+    let mut output: Vec<&[u8]> = Vec::new();
+    let length = segments.len();
+    for (i, &segment) in segments.iter().enumerate() {
+        if segment == &b".."[..] {
+            output.pop();
+        } else if segment != &b"."[..] && !segment.is_empty() {
+            output.push(segment);
+            continue
+        }
+        if i + 1 == length {
+            output.push(b"");
+        }
+    }
+    let mut result = vec![b'/'];
+    result.push_all(output.connect(&b'/').as_slice());
+    result
+}
+
+
+/// Percent-decode repeatedly, until there is nothing left to decode.
+fn unescape(input: &[u8]) -> Vec<u8> {
+    let mut output = input.to_vec();
+    loop {
+        let decoded = percent_decode(output.as_slice());
+        if decoded == output {
+            return output
+        }
+        output = decoded;
+    }
+}
+
+
+/// Percent-encode control characters, space, non-ASCII bytes, `#` and `%`.
+fn escape(input: &[u8]) -> String {
+    let mut output = String::with_capacity(input.len());
+    for &byte in input.iter() {
+        if byte <= 0x20 || byte >= 0x7F || byte == b'#' || byte == b'%' {
+            output.push_str(format!("%{:02X}", byte).as_slice());
+        } else {
+            output.push(byte as char);
+        }
+    }
+    output
+}
+
+
+fn trim(input: &[u8]) -> &[u8] {
+    let start = input.iter().position(|&b| b > 0x20).unwrap_or(input.len());
+    let end = input.iter().rposition(|&b| b > 0x20).map_or(start, |position| position + 1);
+    &input[start..end]
+}
+
+
+fn find(input: &[u8], needle: &[u8]) -> Option<usize> {
+    input.windows(needle.len()).position(|window| window == needle)
+}
+
+
+fn is_scheme(input: &[u8]) -> bool {
+    !input.is_empty() && matches!(input[0], b'a'...b'z' | b'A'...b'Z') &&
+        input.iter().all(|&b| matches!(b, b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' |
+                                          b'+' | b'-' | b'.'))
+}
@@ -0,0 +1,192 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Find runs of bytes that the parser and the percent-encoder copy unchanged.
+//!
+//! The bytes that end a run are given as a `ByteSet`, a 256-bit table.
+//! Sets never contain ASCII letters or digits, which make up most of real-world URLs.
+//! Bytes are tested eight at a time in an `u64`, “SIMD within a register”:
+//! a few additions mark the letters and digits of the word in the high bit of each byte,
+//! without carries between bytes, and the first of the other bytes that is in the table
+//! ends the run. The last bytes, fewer than eight, are looked up one by one.
+//!
+//! With the `scalar_scan` Cargo feature, all bytes are looked up one by one,
+//! to compare with the word-at-a-time scan in `cargo bench`.
+
+use std::num::Int;
+
+
+const ONES: u64 = 0x0101_0101_0101_0101;
+const LOW_BITS: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+
+/// A set of bytes, with one bit for each of the 256 byte values.
+/// It never contains ASCII letters or digits.
+#[derive(Copy, Clone)]
+pub struct ByteSet {
+    bits: [u64; 4],
+}
+
+
+impl ByteSet {
+    /// Return the set of the bytes outside of the printable ASCII range,
+    /// from 0x20 space to 0x7E `~`, and of the bytes in `stops`.
+    pub fn non_printable_and(stops: &[&[u8]]) -> ByteSet {
+        // 0x00 to 0x1F, 0x7F, and 0x80 to 0xFF.
+        let mut set = ByteSet::from_bits([0xFFFF_FFFF, 1 << 63, !0, !0]);
+        for bytes in stops.iter() {
+            for &byte in bytes.iter() {
+                debug_assert!(!matches!(byte, b'0'...b'9' | b'A'...b'Z' | b'a'...b'z'));
+                set.bits[(byte >> 6) as usize] |= 1 << ((byte & 63) as usize);
+            }
+        }
+        set
+    }
+
+    /// Return the set of the bytes whose bit is set in `bits`,
+    /// as generated by make_encode_sets.py.
+    #[inline]
+    pub fn from_bits(bits: [u64; 4]) -> ByteSet {
+        ByteSet { bits: bits }
+    }
+
+    #[inline]
+    pub fn contains(&self, byte: u8) -> bool {
+        (self.bits[(byte >> 6) as usize] >> ((byte & 63) as usize)) & 1 != 0
+    }
+}
+
+
+/// Return the length of the run of bytes at the start of `input` that are not in `stops`.
+#[inline]
+pub fn run_length(input: &[u8], stops: &ByteSet) -> usize {
+    let mut i = 0;
+    if !cfg!(feature = "scalar_scan") {
+        while i + 8 <= input.len() {
+            // Bytes that are not letters or digits, most often none or one per word.
+            let mut others = !alphanumeric(read_word(&input[i..i + 8])) & HIGH_BITS;
+            while others != 0 {
+                let j = (others.trailing_zeros() as usize) / 8;
+                if stops.contains(input[i + j]) {
+                    return i + j
+                }
+                // Clear the lowest set bit.
+                others &= others - 1;
+            }
+            i += 8;
+        }
+    }
+    while i < input.len() && !stops.contains(input[i]) {
+        i += 1;
+    }
+    i
+}
+
+
+/// Read eight bytes in little-endian order, so that the first byte is the least significant.
+/// Compilers turn this into a single (unaligned) load.
+#[inline]
+fn read_word(bytes: &[u8]) -> u64 {
+    let mut word = 0;
+    for (j, &byte) in bytes.iter().enumerate() {
+        word |= (byte as u64) << (8 * j);
+    }
+    word
+}
+
+
+/// Set the high bit of each byte of `word` that is an ASCII letter or digit,
+/// and clear all other bits.
+#[inline]
+fn alphanumeric(word: u64) -> u64 {
+    let digits = in_range(word, b'0', b'9');
+    // Setting 0x20 maps `A` to `Z` to `a` to `z`, and no other byte into that range.
+    let letters = in_range(word | 0x20 * ONES, b'a', b'z');
+    (digits | letters) & !word & HIGH_BITS
+}
+
+
+/// Set the high bit of each byte of `word` whose low seven bits are between `low` and `high`,
+/// inclusive. The other bits are meaningless.
+#[inline]
+fn in_range(word: u64, low: u8, high: u8) -> u64 {
+    let word = word & LOW_BITS;
+    // Adding to the low seven bits never carries into the next byte.
+    // The high bit is set if the byte is at least `low`, and if it is more than `high`.
+    let at_least_low = word + (0x80 - low as u64) * ONES;
+    let more_than_high = word + (0x7F - high as u64) * ONES;
+    at_least_low & !more_than_high
+}
+
+
+#[test]
+fn test_run_length() {
+    let stop_bytes: &[&[u8]] = &[b"%/", b"?"];
+    let stops = ByteSet::non_printable_and(stop_bytes);
+    let is_stop = |byte: u8| byte < 0x20 || byte > 0x7E || matches!(byte, b'%' | b'/' | b'?');
+    for byte in 0..256 {
+        assert_eq!(stops.contains(byte as u8), is_stop(byte as u8));
+    }
+    // Compare with a byte-by-byte scan, for each byte at each position in a word.
+    for byte in 0..256 {
+        let byte = byte as u8;
+        for position in 0..26 {
+            let mut input = b"abcdefghijklmnopqrstuvwxyz".to_vec();
+            input[position] = byte;
+            let expected = input.iter().position(|&b| is_stop(b)).unwrap_or(input.len());
+            assert_eq!(run_length(input.as_slice(), &stops), expected);
+        }
+    }
+    assert_eq!(run_length(b"", &stops), 0);
+    assert_eq!(run_length(b"abc", &stops), 3);
+    assert_eq!(run_length(b"/search?q=rust", &stops), 0);
+    assert_eq!(run_length(b"search?q=rust", &stops), 6);
+    // Bytes other than letters and digits that are not stops, in the same word as a stop.
+    assert_eq!(run_length(b"a.b-c_d/e", &stops), 7);
+    assert_eq!(run_length(b"~.-_+=!/..", &stops), 7);
+    let stops = ByteSet::non_printable_and(&[]);
+    assert_eq!(run_length(b"0123456789ABCDEF~ \x7F", &stops), 18);
+    assert_eq!(run_length("caf\u{E9}".as_bytes(), &stops), 3);
+}
+
+
+#[test]
+fn percent_encode_matches_table() {
+    use percent_encoding::{
+        percent_encode, SIMPLE_ENCODE_SET, QUERY_ENCODE_SET, DEFAULT_ENCODE_SET,
+        PATH_SEGMENT_ENCODE_SET, MATRIX_PARAM_ENCODE_SET, USERINFO_ENCODE_SET,
+        PASSWORD_ENCODE_SET, USERNAME_ENCODE_SET, FORM_URLENCODED_ENCODE_SET,
+    };
+
+    // Compare with the previous implementation, one table lookup for every byte,
+    // on every byte value, alone and in words, and on the benchmark corpus.
+    let all_bytes: Vec<u8> = (0..256).map(|byte| byte as u8).collect();
+    let single_bytes: Vec<[u8; 1]> = (0..256).map(|byte| [byte as u8]).collect();
+    let mut inputs = vec![all_bytes.as_slice()];
+    inputs.extend(single_bytes.iter().map(|byte| &byte[..]));
+    inputs.extend(include_str!("../benches/urls.txt").lines().map(|line| line.as_bytes()));
+    let encode_sets = [
+        SIMPLE_ENCODE_SET, QUERY_ENCODE_SET, DEFAULT_ENCODE_SET, PATH_SEGMENT_ENCODE_SET,
+        MATRIX_PARAM_ENCODE_SET, USERINFO_ENCODE_SET, PASSWORD_ENCODE_SET, USERNAME_ENCODE_SET,
+        FORM_URLENCODED_ENCODE_SET,
+    ];
+    for &encode_set in encode_sets.iter() {
+        for input in inputs.iter() {
+            let mut expected = String::new();
+            for &byte in input.iter() {
+                if encode_set.contains(byte) {
+                    expected.push_str(format!("%{:02X}", byte).as_slice());
+                } else {
+                    expected.push(byte as char);
+                }
+            }
+            assert_eq!(percent_encode(*input, encode_set), expected);
+        }
+    }
+}
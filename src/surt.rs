@@ -0,0 +1,248 @@
+// Copyright 2015 Simon Sapin.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! [SURT](http://crawler.archive.org/articles/user_manual/glossary.html#surt)
+//! (Sort-friendly URI Reordering Transform) keys, as used by web archives
+//! to index captures in CDX files.
+//!
+//! The labels of the host are reversed and separated by commas,
+//! so that sorting keys groups URLs by domain:
+//! `http://www.example.com/path?b=2&a=1` becomes `com,example)/path?a=1&b=2`
+//! with `SurtOptions::pywb()`, or `http://(com,example,)/path?b=2&a=1`
+//! with `SurtOptions::heritrix()`.
+//!
+//! The fragment identifier and the username and password are always removed,
+//! and the default port is never included.
+//!
+//! ```
+//! use url::Url;
+//! use url::surt::{to_surt, from_surt, SurtOptions};
+//!
+//! let url = Url::parse("http://www.example.com/path?b=2&a=1").unwrap();
+//! assert_eq!(to_surt(&url, SurtOptions::pywb()), "com,example)/path?a=1&b=2".to_string());
+//! assert_eq!(from_surt("com,example)/path?a=1&b=2").unwrap().serialize(),
+//!            "http://example.com/path?a=1&b=2".to_string());
+//! ```
+
+use std::ascii::AsciiExt;
+use super::{Url, Host};
+use parser::{ParseResult, ParseError};
+
+
+/// Query parameters and `;` path parameters that are removed as session IDs,
+/// compared case-insensitively. A trailing `*` matches any suffix.
+static SESSION_ID_PARAMS: &'static [&'static str] = &[
+    "jsessionid", "phpsessid", "sessionid", "aspsessionid*", "cfid", "cftoken",
+];
+
+
+/// The canonicalization steps and output format of a SURT.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct SurtOptions {
+    /// Start with the scheme, and put the host between `(` and `)`, as in `http://(com,example,)/`.
+    /// Otherwise, the scheme is omitted, as in `com,example)/`.
+    pub include_scheme: bool,
+
+    /// Add a comma after the last host label, as in `(com,example,)`.
+    pub trailing_comma: bool,
+
+    /// Remove a `www.` first label, or `www` followed by digits like `www2.`.
+    pub strip_www: bool,
+
+    /// Convert the path and query string to lower case.
+    /// (The scheme and host always are.)
+    pub lowercase: bool,
+
+    /// Remove session ID query parameters such as `jsessionid`, `PHPSESSID` or `CFID`,
+    /// and `;jsessionid=…` path parameters.
+    pub strip_session_ids: bool,
+
+    /// Sort the `&`-separated pairs of the query string.
+    pub sort_query: bool,
+}
+
+
+impl SurtOptions {
+    /// The format of Heritrix’s SURT prefixes, after its default canonicalization rules.
+    pub fn heritrix() -> SurtOptions {
+        SurtOptions {
+            include_scheme: true,
+            trailing_comma: true,
+            strip_www: true,
+            lowercase: true,
+            strip_session_ids: true,
+            sort_query: false,
+        }
+    }
+
+    /// The format of the CDX keys used by pywb and the Wayback Machine.
+    pub fn pywb() -> SurtOptions {
+        SurtOptions {
+            include_scheme: false,
+            trailing_comma: false,
+            strip_www: true,
+            lowercase: true,
+            strip_session_ids: true,
+            sort_query: true,
+        }
+    }
+}
+
+
+/// Return the SURT of `url`.
+///
+/// URLs in a non-relative scheme such as `mailto` have no host to reorder,
+/// and are returned serialized without their fragment identifier.
+pub fn to_surt(url: &Url, options: SurtOptions) -> String {
+    let scheme_data = match url.relative_scheme_data() {
+        Some(scheme_data) => scheme_data,
+        None => return url.serialize_no_fragment(),
+    };
+    let mut surt = String::new();
+    if options.include_scheme {
+        surt.push_str(url.scheme.as_slice());
+        surt.push_str("://(");
+    }
+    match scheme_data.host {
+        Host::Domain(ref domain) => {
+            let domain = domain.as_slice().trim_right_matches('.');
+            let mut labels: Vec<&str> = domain.split('.').collect();
+            if !is_ipv4(labels.as_slice()) {
+                if options.strip_www && labels.len() > 1 && is_www(labels[0]) {
+                    labels.remove(0);
+                }
+                labels.reverse();
+            }
+            surt.push_str(labels.connect(",").as_slice());
+        },
+        ref host => surt.push_str(host.serialize().as_slice()),
+    }
+    if options.trailing_comma {
+        surt.push(',');
+    }
+    if let Some(port) = scheme_data.port {
+        surt.push_str(format!(":{}", port).as_slice());
+    }
+    surt.push(')');
+
+    let mut path = scheme_data.serialize_path();
+    if options.strip_session_ids {
+        path = strip_path_session_ids(path.as_slice());
+    }
+    if options.lowercase {
+        path = path.as_slice().to_ascii_lowercase();
+    }
+    surt.push_str(path.as_slice());
+
+    if let Some(ref query) = url.query {
+        let query = if options.lowercase {
+            query.as_slice().to_ascii_lowercase()
+        } else {
+            query.clone()
+        };
+        let query = if options.strip_session_ids || options.sort_query {
+            let mut pairs: Vec<&str> = query.as_slice().split('&').filter(|&pair| {
+                !(options.strip_session_ids && is_session_id(name(pair)))
+            }).collect();
+            if options.sort_query {
+                pairs.sort();
+            }
+            pairs.connect("&")
+        } else {
+            query
+        };
+        if !query.is_empty() {
+            surt.push('?');
+            surt.push_str(query.as_slice());
+        }
+    }
+    surt
+}
+
+
+/// Parse a SURT back into an URL, with the `http` scheme if it does not include one.
+///
+/// This can not undo the canonicalization steps:
+/// for example, a `www.` label or a session ID that was removed is not restored.
+pub fn from_surt(surt: &str) -> ParseResult<Url> {
+    let (scheme, rest) = match surt.find("://(") {
+        Some(position) => (&surt[..position], &surt[position + 4..]),
+        None => ("http", surt),
+    };
+    let close = match rest.find(')') {
+        Some(position) => position,
+        None => return Err(ParseError::InvalidSurt),
+    };
+    let (host_and_port, path) = (&rest[..close], &rest[close + 1..]);
+    let port_start = if host_and_port.starts_with("[") {
+        host_and_port.find(']').and_then(|end| {
+            host_and_port[end..].find(':').map(|colon| end + colon)
+        })
+    } else {
+        host_and_port.rfind(':')
+    };
+    let (host, port) = match port_start {
+        Some(position) => (&host_and_port[..position], &host_and_port[position..]),
+        None => (host_and_port, ""),
+    };
+    let host = host.trim_right_matches(',');
+    if host.is_empty() {
+        return Err(ParseError::InvalidSurt)
+    }
+    let mut labels: Vec<&str> = host.split(',').collect();
+    if !is_ipv4(labels.as_slice()) && !host.starts_with("[") {
+        labels.reverse();
+    }
+    let path = if path.is_empty() { "/" } else { path };
+    Url::parse(format!("{}://{}{}{}", scheme, labels.connect("."), port, path).as_slice())
+}
+
+
+fn is_ipv4(labels: &[&str]) -> bool {
+    labels.len() == 4 && labels.iter().all(|label| {
+        !label.is_empty() && label.bytes().all(|b| matches!(b, b'0'...b'9'))
+    })
+}
+
+
+fn is_www(label: &str) -> bool {
+    label.starts_with("www") && label[3..].bytes().all(|b| matches!(b, b'0'...b'9'))
+}
+
+
+fn is_session_id(name: &str) -> bool {
+    SESSION_ID_PARAMS.iter().any(|&param| {
+        if param.ends_with("*") {
+            let prefix = &param[..param.len() - 1];
+            name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix)
+        } else {
+            name.eq_ignore_ascii_case(param)
+        }
+    })
+}
+
+
+#[inline]
+fn name(pair: &str) -> &str {
+    &pair[..pair.find('=').unwrap_or(pair.len())]
+}
+
+
+fn strip_path_session_ids(path: &str) -> String {
+    path.split('/').map(|segment| {
+        let mut parts = segment.split(';');
+        let mut result = parts.next().unwrap_or("").to_string();
+        for param in parts {
+            if !is_session_id(name(param)) {
+                result.push(';');
+                result.push_str(param);
+            }
+        }
+        result
+    }).collect::<Vec<String>>().connect("/")
+}